@@ -0,0 +1,142 @@
+//! Async client for tests running on a `tokio` runtime, behind the `async` feature.
+//!
+//! The futures of the client are cancellation-safe: dropping one, e.g. when it loses a
+//! `tokio::select!` against a timeout, aborts its in-flight request and closes the connection.
+//! Nothing keeps running in the background.
+
+use reqwest::{Client, RequestBuilder, Url};
+use std::net::ToSocketAddrs;
+use std::time::{Duration, Instant};
+
+use super::consts::*;
+use super::error::*;
+use super::http_client::*;
+use super::version::*;
+
+/// Async server client.
+#[derive(Clone, Debug)]
+pub struct AsyncClient {
+    client: Client,
+    base: Url,
+}
+
+impl AsyncClient {
+    /// Creates a new async client. Requests time out after 10 seconds, like the ones of the
+    /// blocking client. Fails when the address doesn't resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::async_client::AsyncClient;
+    /// let client = AsyncClient::new("127.0.0.1:8474").expect("address resolves");
+    /// ```
+    pub fn new<U: ToSocketAddrs>(toxiproxy_addr: U) -> Result<Self, ToxiproxyError> {
+        let client = Client::builder()
+            .timeout(DEFAULT_REQUEST_TIMEOUT)
+            .connect_timeout(DEFAULT_CONNECT_TIMEOUT)
+            .build()?;
+        let base = Url::parse(&format!("http://{}", resolve(toxiproxy_addr)?[0]))
+            .map_err(|err| format!("Incorrect address: {}", err))?;
+
+        Ok(Self { client, base })
+    }
+
+    /// Checks if the server answers requests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::async_client::AsyncClient;
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # runtime.block_on(async {
+    /// let client = AsyncClient::new("127.0.0.1:8474").unwrap();
+    /// if !client.is_running().await {
+    ///     /* signal the problem */
+    /// }
+    /// # });
+    /// ```
+    pub async fn is_running(&self) -> bool {
+        self.version().await.is_ok()
+    }
+
+    /// Waits until the server answers requests, e.g. right after starting it, failing once
+    /// `timeout` passes. Dropping the future stops the polling and aborts the pending request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use toxiproxy_rust::async_client::AsyncClient;
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # runtime.block_on(async {
+    /// AsyncClient::new("127.0.0.1:8474")
+    ///     .unwrap()
+    ///     .wait_until_running(Duration::from_secs(5))
+    ///     .await
+    ///     .expect("server is running");
+    /// # });
+    /// ```
+    pub async fn wait_until_running(&self, timeout: Duration) -> Result<(), ToxiproxyError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // A hung server doesn't hold the caller past the deadline.
+            let left = deadline.saturating_duration_since(Instant::now());
+            if self.send(self.get("version")?.timeout(left)).await.is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(format!("server is not running after {:?}", timeout).into());
+            }
+            tokio::time::sleep(RUNNING_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Version of the Toxiproxy server, see [`Client::version`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::async_client::AsyncClient;
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # runtime.block_on(async {
+    /// let version = AsyncClient::new("127.0.0.1:8474")
+    ///     .unwrap()
+    ///     .version()
+    ///     .await
+    ///     .expect("version is returned");
+    /// println!("{}", version.raw);
+    /// # });
+    /// ```
+    ///
+    /// [`Client::version`]: ../client/struct.Client.html#method.version
+    pub async fn version(&self) -> Result<ServerVersion, ToxiproxyError> {
+        self.send(self.get("version")?)
+            .await
+            .map(ServerVersion::new)
+    }
+
+    fn get(&self, path: &str) -> Result<RequestBuilder, ToxiproxyError> {
+        let url = self
+            .base
+            .join(path)
+            .map_err(|err| format!("Incorrect address: {}", err))?;
+
+        Ok(self.client.get(url))
+    }
+
+    /// Sends `request`, returning the body of a successful response. For an error status the
+    /// message of the server is returned instead, like with the blocking client.
+    async fn send(&self, request: RequestBuilder) -> Result<String, ToxiproxyError> {
+        let response = request
+            .header("Content-Type", "application/json")
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(server_error(status, &body));
+        }
+
+        Ok(body)
+    }
+}
@@ -2,21 +2,65 @@
 
 use serde_json;
 use std::net::ToSocketAddrs;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::{collections::HashMap, io::Read};
+use std::time::{Duration, Instant};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+};
 
+use super::consts::*;
+use super::error::*;
 use super::http_client::*;
+use super::metrics::*;
 use super::proxy::*;
+use super::scenario::*;
+use super::transaction::*;
+use super::version::*;
+
+/// Suffix of the next proxy created by [`Client::create_proxy_unique`].
+static UNIQUE_PROXY_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /// Server client.
 #[derive(Clone)]
 pub struct Client {
     client: Arc<Mutex<HttpClient>>,
+    reset_hooks: Arc<Mutex<Vec<ResetHook>>>,
+    scenarios: Arc<Mutex<HashMap<String, InstalledToxics>>>,
+}
+
+type ResetHook = Arc<dyn Fn(ResetEvent) + Send + Sync>;
+
+/// `(proxy, toxic)` names installed by an applied scenario.
+type InstalledToxics = Vec<(String, String)>;
+
+/// Moment a hook registered with [`Client::on_reset`] is called at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetEvent {
+    BeforeReset,
+    AfterReset,
+    BeforePopulate,
+    AfterPopulate,
+}
+
+/// Result of [`Client::all_lenient`]: the proxies which could be read and, by name, why the
+/// others couldn't.
+#[derive(Debug)]
+pub struct LenientProxies {
+    pub proxies: HashMap<String, Proxy>,
+    pub malformed: HashMap<String, String>,
 }
 
 impl Client {
     /// Creates a new client. There is also a prepopulated client, `toxiproxy_rust::TOXIPROXY`
-    /// connected to the server's default address.
+    /// connected to the server's default address. Requests time out after 10 seconds, see
+    /// [`with_timeouts`] to change that.
+    ///
+    /// # Panics
+    ///
+    /// When `toxiproxy_addr` doesn't resolve to a socket address. [`with_timeouts`] and
+    /// [`from_uri`] return an error instead.
     ///
     /// # Examples
     ///
@@ -24,13 +68,268 @@ impl Client {
     /// # use toxiproxy_rust::client::Client;
     /// let client = Client::new("127.0.0.1:8474");
     /// ```
+    ///
+    /// [`with_timeouts`]: #method.with_timeouts
+    /// [`from_uri`]: #method.from_uri
     pub fn new<U: ToSocketAddrs>(toxiproxy_addr: U) -> Self {
+        HttpClient::new(toxiproxy_addr)
+            .map(Self::from_http_client)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new client whose requests fail after `request_timeout`, or after
+    /// `connect_timeout` when the server cannot be connected to - so a hung server doesn't hang
+    /// the tests. The defaults are 10 and 2 seconds. Fails if `toxiproxy_addr` doesn't resolve
+    /// to a socket address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::with_timeouts(
+    ///     "127.0.0.1:8474",
+    ///     Duration::from_secs(2),
+    ///     Duration::from_millis(500),
+    /// )
+    /// .expect("client is built");
+    /// ```
+    pub fn with_timeouts<U: ToSocketAddrs>(
+        toxiproxy_addr: U,
+        request_timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<Self, ToxiproxyError> {
+        let client = HttpClient::build_client(request_timeout, connect_timeout)?;
+
+        HttpClient::with_client(toxiproxy_addr, client).map(Self::from_http_client)
+    }
+
+    /// Creates a new client from a URL (behind the `url` feature). The URL is checked right away:
+    /// it must use `http` and have a host that resolves. The port defaults to 8474 and a path is
+    /// used as [base path].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::from_url(url::Url::parse("http://127.0.0.1:8474").unwrap())
+    ///     .expect("URL is valid");
+    /// ```
+    ///
+    /// [base path]: #method.with_base_path
+    #[cfg(feature = "url")]
+    pub fn from_url(url: url::Url) -> Result<Self, ToxiproxyError> {
+        Self::from_parsed_url(url)
+    }
+
+    /// Creates a new client from a URI like `http://toxiproxy:8474`, or just `toxiproxy:8474`,
+    /// e.g. taken from an environment variable. Checked right away like [`from_url`], which
+    /// needs the `url` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::from_uri("http://localhost:8474").expect("URI is valid");
+    /// ```
+    ///
+    /// [`from_url`]: #method.from_url
+    pub fn from_uri(uri: impl Into<String>) -> Result<Self, ToxiproxyError> {
+        let mut uri = uri.into();
+        if !uri.contains("://") {
+            uri.insert_str(0, "http://");
+        }
+        let url =
+            reqwest::Url::parse(&uri).map_err(|err| format!("invalid URI `{}`: {}", uri, err))?;
+
+        Self::from_parsed_url(url)
+    }
+
+    /// Creates a new client for the URI in the `TOXIPROXY_URL` environment variable, checked like
+    /// with [`from_uri`]. Falls back to the default address when the variable is unset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::from_env().expect("TOXIPROXY_URL is valid");
+    /// ```
+    ///
+    /// [`from_uri`]: #method.from_uri
+    pub fn from_env() -> Result<Self, ToxiproxyError> {
+        match std::env::var(crate::TOXIPROXY_URL_ENV) {
+            Ok(uri) => Self::from_uri(uri),
+            Err(std::env::VarError::NotPresent) => Self::from_uri(crate::TOXIPROXY_DEFAULT_URI),
+            Err(err) => Err(format!("{} cannot be read: {}", crate::TOXIPROXY_URL_ENV, err).into()),
+        }
+    }
+
+    fn from_parsed_url(url: reqwest::Url) -> Result<Self, ToxiproxyError> {
+        if url.scheme() != "http" {
+            return Err(format!("unsupported scheme of {}, expected http", url).into());
+        }
+        let toxiproxy_addrs = url
+            .socket_addrs(|| Some(8474))
+            .map_err(|err| format!("{} cannot be resolved: {}", url, err))?;
+        if toxiproxy_addrs.is_empty() {
+            return Err(format!("{} cannot be resolved", url).into());
+        }
+
+        Ok(
+            Self::from_http_client(HttpClient::new(&toxiproxy_addrs[..])?)
+                .with_base_path(url.path()),
+        )
+    }
+
+    /// Creates a new client sending its requests with an already configured `reqwest` client,
+    /// e.g. one with custom timeouts, TLS or proxy settings.
+    ///
+    /// # Panics
+    ///
+    /// When `toxiproxy_addr` doesn't resolve to a socket address, like [`new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use toxiproxy_rust::client::Client;
+    /// let http_client = reqwest::blocking::Client::builder()
+    ///     .timeout(Duration::from_secs(5))
+    ///     .build()
+    ///     .unwrap();
+    /// let client = Client::with_client("127.0.0.1:8474", http_client);
+    /// ```
+    ///
+    /// [`new`]: #method.new
+    pub fn with_client<U: ToSocketAddrs>(
+        toxiproxy_addr: U,
+        client: reqwest::blocking::Client,
+    ) -> Self {
+        HttpClient::with_client(toxiproxy_addr, client)
+            .map(Self::from_http_client)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a client for another server which shares the connection pool, and all `reqwest`
+    /// settings, of this one. Saves connections and file descriptors when a large test suite
+    /// talks to several servers. Other settings, like the [base path], start out at their
+    /// defaults. Fails if `toxiproxy_addr` doesn't resolve to a socket address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let first = Client::new("127.0.0.1:8474");
+    /// let second = first.sharing_pool("localhost:8474").unwrap();
+    /// ```
+    ///
+    /// [base path]: #method.with_base_path
+    pub fn sharing_pool<U: ToSocketAddrs>(
+        &self,
+        toxiproxy_addr: U,
+    ) -> Result<Self, ToxiproxyError> {
+        // Resolving may take a while, so it happens without holding the lock.
+        let client = lock_client(&self.client).clone();
+
+        Ok(Self::from_http_client(client.with_address(toxiproxy_addr)?))
+    }
+
+    fn from_http_client(client: HttpClient) -> Self {
         Self {
-            client: Arc::new(Mutex::new(HttpClient::new(toxiproxy_addr))),
+            client: Arc::new(Mutex::new(client)),
+            reset_hooks: Arc::new(Mutex::new(Vec::new())),
+            scenarios: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Establish a set of proxies to work with.
+    /// Registers a hook called before and after every [`reset`] and [`populate`] - also when the
+    /// call fails - e.g. for a test framework to log or snapshot state. Clones of the client
+    /// share their hooks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::new("127.0.0.1:8474");
+    /// client.on_reset(|event| println!("toxiproxy: {:?}", event));
+    /// client.reset().unwrap();
+    /// ```
+    ///
+    /// [`reset`]: #method.reset
+    /// [`populate`]: #method.populate
+    pub fn on_reset<F>(&self, hook: F)
+    where
+        F: Fn(ResetEvent) + Send + Sync + 'static,
+    {
+        lock(&self.reset_hooks).push(Arc::new(hook));
+    }
+
+    fn with_reset_hooks<T, F>(
+        &self,
+        before: ResetEvent,
+        after: ResetEvent,
+        operation: F,
+    ) -> Result<T, ToxiproxyError>
+    where
+        F: FnOnce() -> Result<T, ToxiproxyError>,
+    {
+        let hooks = lock(&self.reset_hooks).clone();
+
+        hooks.iter().for_each(|hook| hook(before));
+        let result = operation();
+        hooks.iter().for_each(|hook| hook(after));
+
+        result
+    }
+
+    /// Sets a path prefix for all requests, for when the server is mounted under a sub-path
+    /// (e.g. behind a reverse proxy).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::new("127.0.0.1:8474").with_base_path("/toxiproxy");
+    /// ```
+    pub fn with_base_path(self, base_path: &str) -> Self {
+        lock_client(&self.client).set_base_path(base_path);
+        self
+    }
+
+    /// Limits the size of response bodies read from the server. Calls whose response exceeds
+    /// `max_response_size` bytes fail with a "Response too large" error instead of buffering
+    /// the whole body. Unlimited by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::new("127.0.0.1:8474").with_max_response_size(1024 * 1024);
+    /// ```
+    pub fn with_max_response_size(self, max_response_size: u64) -> Self {
+        lock_client(&self.client).set_max_response_size(max_response_size);
+        self
+    }
+
+    /// Stops adding the `Content-Type: application/json` header to requests, e.g. for gateways
+    /// that are strict about headers. Headers of your own can be set as default headers of a
+    /// client passed to [`with_client`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// let client = Client::new("127.0.0.1:8474").without_content_type();
+    /// ```
+    ///
+    /// [`with_client`]: #method.with_client
+    pub fn without_content_type(self) -> Self {
+        lock_client(&self.client).disable_content_type();
+        self
+    }
+
+    /// Establish a set of proxies to work with. Fails when the server answers without some of the
+    /// requested proxies, e.g. because one could not bind its port.
     ///
     /// # Examples
     ///
@@ -52,28 +351,291 @@ impl Client {
     ///     "localhost:2000".into(),
     /// )]).expect("populate has completed");
     /// ```
-    pub fn populate(&self, proxies: Vec<ProxyPack>) -> Result<Vec<Proxy>, String> {
+    pub fn populate(&self, proxies: Vec<ProxyPack>) -> Result<Vec<Proxy>, ToxiproxyError> {
         let proxies_json = serde_json::to_string(&proxies).unwrap();
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
-            .post_with_data("populate", proxies_json)
-            .and_then(|response| {
-                response
-                    .json::<HashMap<String, Vec<ProxyPack>>>()
-                    .map_err(|err| format!("json deserialize failed: {}", err))
+        self.with_reset_hooks(
+            ResetEvent::BeforePopulate,
+            ResetEvent::AfterPopulate,
+            || {
+                lock_client(&self.client)
+                    .post_with_data("populate", proxies_json)
+                    .and_then(read_json::<HashMap<String, Vec<ProxyPack>>>)
+                    .map(|ref mut response_obj| response_obj.remove("proxies").unwrap_or(vec![]))
+                    .and_then(|proxy_packs| {
+                        check_created(&proxies, &proxy_packs)?;
+                        for proxy_pack in &proxy_packs {
+                            check_listen(&proxies, proxy_pack)?;
+                        }
+                        Ok(proxy_packs)
+                    })
+                    .map(|proxy_packs| {
+                        proxy_packs
+                            .into_iter()
+                            .map(|proxy_pack| Proxy::new(proxy_pack, self.client.clone()))
+                            .collect::<Vec<Proxy>>()
+                    })
+            },
+        )
+    }
+
+    /// Establish a set of proxies to work with, returned by their name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxies = toxiproxy_rust::TOXIPROXY.populate_map(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    ///     "socket".into(),
+    ///     "localhost:2001".into(),
+    ///     "localhost:2000".into(),
+    /// )]).expect("populate has completed");
+    /// let socket = &proxies["socket"];
+    /// ```
+    pub fn populate_map(
+        &self,
+        proxies: Vec<ProxyPack>,
+    ) -> Result<HashMap<String, Proxy>, ToxiproxyError> {
+        self.populate(proxies).map(|proxies| {
+            proxies
+                .into_iter()
+                .map(|proxy| (proxy.proxy_pack.name.clone(), proxy))
+                .collect()
+        })
+    }
+
+    /// Runs a multi-step setup with all-or-nothing semantics. Proxies and toxics created through
+    /// the given [`Transaction`] are deleted again (best-effort, newest first) when the closure
+    /// returns an error, which is then passed on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::error::ToxiproxyError;
+    /// # use toxiproxy_rust::proxy::ProxyPack;
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// let result: Result<(), ToxiproxyError> = toxiproxy_rust::TOXIPROXY.transaction(|transaction| {
+    ///     let proxy = transaction.proxy(ProxyPack::new(
+    ///         "transaction_example".into(),
+    ///         "localhost:2011".into(),
+    ///         "localhost:2000".into(),
+    ///     ))?;
+    ///     transaction.toxic(&proxy, ToxicBuilder::new("latency".into()).build()?)?;
+    ///     Ok(())
+    /// });
+    /// assert!(result.is_err());
+    /// ```
+    ///
+    /// [`Transaction`]: ../transaction/struct.Transaction.html
+    pub fn transaction<F, T>(&self, closure: F) -> Result<T, ToxiproxyError>
+    where
+        F: FnOnce(&Transaction) -> Result<T, ToxiproxyError>,
+    {
+        let transaction = Transaction::new(self.client.clone());
+
+        let result = closure(&transaction);
+        if result.is_err() {
+            transaction.rollback();
+        }
+
+        result
+    }
+
+    /// Installs the toxics of `scenario` on its proxies, all or nothing - when one of them can't
+    /// be created the ones already installed are removed again. A scenario can only be applied
+    /// once until it is [cleared].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::{scenario::Scenario, toxic::ToxicBuilder};
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// # toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let scenario = Scenario::new("slow-network").with_toxic(
+    ///     "socket",
+    ///     ToxicBuilder::new("latency".into()).attribute("latency".into(), 1000).build().unwrap(),
+    /// );
+    /// toxiproxy_rust::TOXIPROXY.apply_scenario(&scenario).unwrap();
+    /// # toxiproxy_rust::TOXIPROXY.clear_scenario(&scenario).unwrap();
+    /// ```
+    ///
+    /// [cleared]: #method.clear_scenario
+    pub fn apply_scenario(&self, scenario: &Scenario) -> Result<(), ToxiproxyError> {
+        let mut scenarios = lock(&self.scenarios);
+        if scenarios.contains_key(&scenario.name) {
+            return Err(format!("scenario `{}` is already applied", scenario.name).into());
+        }
+
+        let installed = self.transaction(|transaction| {
+            let mut installed = Vec::new();
+            for (proxy_name, toxics) in scenario.toxics() {
+                let proxy = self.proxy_handle(proxy_name);
+                for toxic in toxics {
+                    let toxic = transaction.toxic(&proxy, toxic.clone())?;
+                    installed.push((proxy_name.clone(), toxic.name));
+                }
+            }
+            Ok(installed)
+        })?;
+        scenarios.insert(scenario.name.clone(), installed);
+
+        Ok(())
+    }
+
+    /// Removes exactly the toxics installed by [`apply_scenario`], leaving any other toxic on the
+    /// proxies in place. Every toxic is tried, the first failure is returned - the toxics which
+    /// could not be removed stay tracked, so clearing the scenario again retries just those.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::scenario::Scenario;
+    /// let result = toxiproxy_rust::TOXIPROXY.clear_scenario(&Scenario::new("never-applied"));
+    /// assert!(result.is_err());
+    /// ```
+    ///
+    /// [`apply_scenario`]: #method.apply_scenario
+    pub fn clear_scenario(&self, scenario: &Scenario) -> Result<(), ToxiproxyError> {
+        let mut scenarios = lock(&self.scenarios);
+        let installed = scenarios
+            .remove(&scenario.name)
+            .ok_or_else(|| format!("scenario `{}` is not applied", scenario.name))?;
+
+        let client = lock_client(&self.client);
+        let mut result = Ok(());
+        let mut remaining = InstalledToxics::new();
+        for (proxy_name, toxic_name) in installed {
+            let removed = client
+                .delete(&format!("proxies/{}/toxics/{}", proxy_name, toxic_name))
+                .and_then(check_status);
+            if let Err(err) = removed {
+                remaining.push((proxy_name, toxic_name));
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        if !remaining.is_empty() {
+            scenarios.insert(scenario.name.clone(), remaining);
+        }
+
+        result
+    }
+
+    /// Like [`populate`], but only sends the proxies which differ from the ones on the server, so
+    /// unchanged proxies aren't rebound and keep their connections. Proxies are compared by
+    /// upstream, enabled state and listen port (a requested port of 0 matches any). As with
+    /// `populate`, proxies on the server that are not listed are left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxies = toxiproxy_rust::TOXIPROXY.populate_if_changed(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    ///     "socket".into(),
+    ///     "localhost:2001".into(),
+    ///     "localhost:2000".into(),
+    /// )]).expect("populate has completed");
+    /// ```
+    ///
+    /// [`populate`]: #method.populate
+    pub fn populate_if_changed(
+        &self,
+        proxies: Vec<ProxyPack>,
+    ) -> Result<Vec<Proxy>, ToxiproxyError> {
+        let mut active = self.all()?;
+
+        let changed = proxies
+            .iter()
+            .filter(|proxy_pack| {
+                !active
+                    .get(&proxy_pack.name)
+                    .is_some_and(|proxy| proxy_pack.same_config(&proxy.proxy_pack))
             })
-            .map(|ref mut response_obj| response_obj.remove("proxies").unwrap_or(vec![]))
-            .map(|proxy_packs| {
-                proxy_packs
-                    .into_iter()
-                    .map(|proxy_pack| Proxy::new(proxy_pack, self.client.clone()))
-                    .collect::<Vec<Proxy>>()
+            .cloned()
+            .collect::<Vec<ProxyPack>>();
+        let mut populated = if changed.is_empty() {
+            HashMap::new()
+        } else {
+            self.populate_map(changed)?
+        };
+
+        proxies
+            .iter()
+            .map(|proxy_pack| {
+                populated
+                    .remove(&proxy_pack.name)
+                    .or_else(|| active.remove(&proxy_pack.name))
+                    .ok_or_else(|| format!("proxy `{}` was not populated", proxy_pack.name).into())
             })
+            .collect()
+    }
+
+    /// Creates a single proxy, leaving the other proxies on the server alone (unlike
+    /// [`populate`]). Fails with the server's conflict error if the name is taken, and like
+    /// [`populate`] if the server bound the proxy to another port than requested.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxy = toxiproxy_rust::TOXIPROXY
+    ///     .create_proxy("redis", "127.0.0.1:0", "localhost:6379")
+    ///     .expect("proxy was created");
+    /// # proxy.delete().unwrap();
+    /// ```
+    ///
+    /// [`populate`]: #method.populate
+    pub fn create_proxy(
+        &self,
+        name: &str,
+        listen: &str,
+        upstream: &str,
+    ) -> Result<Proxy, ToxiproxyError> {
+        let requested = ProxyPack::new(name.into(), listen.into(), upstream.into());
+        let proxy_pack = requested.create(&lock_client(&self.client))?;
+        check_listen(&[requested], &proxy_pack)?;
+
+        Ok(Proxy::new(proxy_pack, self.client.clone()))
+    }
+
+    /// Creates a proxy named after `base_name` with a suffix unique to this process and call, so
+    /// parallel tests (also in other processes) don't collide on a shared server. The name used
+    /// is in the `proxy_pack` of the returned proxy. Pass a listen port of 0 to have the server
+    /// pick a free port as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxy = toxiproxy_rust::TOXIPROXY
+    ///     .create_proxy_unique("redis", "127.0.0.1:0", "localhost:6379")
+    ///     .expect("proxy was created");
+    /// println!("created {} on {}", proxy.proxy_pack.name, proxy.proxy_pack.listen);
+    /// # proxy.delete().unwrap();
+    /// ```
+    pub fn create_proxy_unique(
+        &self,
+        base_name: &str,
+        listen: &str,
+        upstream: &str,
+    ) -> Result<Proxy, ToxiproxyError> {
+        let name = format!(
+            "{}_{}_{}",
+            base_name,
+            std::process::id(),
+            UNIQUE_PROXY_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        self.create_proxy(&name, listen, upstream)
     }
 
     /// Enable all proxies and remove all active toxics.
     ///
+    /// No other thread of this process sends requests through any client while the reset - and
+    /// its [reset hooks] - run, so a setup running concurrently either completes before the reset
+    /// or starts after it. The same holds for the other `reset_*` methods. Other processes using
+    /// the same server are not coordinated.
+    ///
     /// # Examples
     ///
     /// ```
@@ -86,12 +648,89 @@ impl Client {
     /// ```
     /// toxiproxy_rust::TOXIPROXY.reset();
     /// ```
-    pub fn reset(&self) -> Result<(), String> {
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
-            .post("reset")
-            .map(|_| ())
+    ///
+    /// [reset hooks]: #method.on_reset
+    pub fn reset(&self) -> Result<(), ToxiproxyError> {
+        exclusively(|| {
+            self.with_reset_hooks(ResetEvent::BeforeReset, ResetEvent::AfterReset, || {
+                lock_client(&self.client)
+                    .post("reset")
+                    .and_then(check_status)
+                    .map(|_| ())
+            })
+        })
+    }
+
+    /// Like [`reset`], but afterwards checks that no proxy has toxics left, failing if any do.
+    /// Guards test isolation against servers that acknowledge a reset without clearing state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// toxiproxy_rust::TOXIPROXY.reset_verified().expect("reset has completed");
+    /// ```
+    ///
+    /// [`reset`]: #method.reset
+    pub fn reset_verified(&self) -> Result<(), ToxiproxyError> {
+        exclusively(|| {
+            self.reset()?;
+
+            let mut dirty = self
+                .all()?
+                .into_iter()
+                .filter(|(_, proxy)| !proxy.proxy_pack.toxics.is_empty())
+                .map(|(name, _)| name)
+                .collect::<Vec<String>>();
+            if dirty.is_empty() {
+                return Ok(());
+            }
+
+            dirty.sort();
+            Err(format!("reset left toxics on proxies: {}", dirty.join(", ")).into())
+        })
+    }
+
+    /// Enable all proxies and remove their active toxics - except for the proxies named in `keep`,
+    /// which are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// toxiproxy_rust::TOXIPROXY.reset_except(&["socket"]).expect("reset has completed");
+    /// ```
+    pub fn reset_except(&self, keep: &[&str]) -> Result<(), ToxiproxyError> {
+        exclusively(|| {
+            for (name, proxy) in self.all()? {
+                if keep.contains(&name.as_str()) {
+                    continue;
+                }
+
+                proxy.delete_all_toxics()?;
+                proxy.enable()?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Remove all active toxics like [`reset`], but keep disabled proxies disabled. The toxics are
+    /// deleted proxy by proxy, so a disabled proxy doesn't accept connections at any point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// toxiproxy_rust::TOXIPROXY.reset_toxics_only().expect("reset has completed");
+    /// ```
+    ///
+    /// [`reset`]: #method.reset
+    pub fn reset_toxics_only(&self) -> Result<(), ToxiproxyError> {
+        exclusively(|| {
+            for proxy in self.all()?.values() {
+                proxy.delete_all_toxics()?;
+            }
+
+            Ok(())
+        })
     }
 
     /// Returns all registered proxies and their toxics.
@@ -101,24 +740,297 @@ impl Client {
     /// ```
     /// let proxies = toxiproxy_rust::TOXIPROXY.all().expect("all proxies were fetched");
     /// ```
-    pub fn all(&self) -> Result<HashMap<String, Proxy>, String> {
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
+    pub fn all(&self) -> Result<HashMap<String, Proxy>, ToxiproxyError> {
+        lock_client(&self.client)
             .get("proxies")
             .and_then(|response| {
-                response
-                    .json()
-                    .map(|proxy_map: HashMap<String, ProxyPack>| {
-                        proxy_map
-                            .into_iter()
-                            .map(|(name, proxy_pack)| {
-                                (name, Proxy::new(proxy_pack, self.client.clone()))
-                            })
-                            .collect()
+                read_json(response).map(|proxy_map: HashMap<String, ProxyPack>| {
+                    proxy_map
+                        .into_iter()
+                        .map(|(name, proxy_pack)| {
+                            (name, Proxy::new(proxy_pack, self.client.clone()))
+                        })
+                        .collect()
+                })
+            })
+    }
+
+    /// Returns the enabled proxies, sorted by name - e.g. for operations that only make sense on
+    /// live proxies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxies = toxiproxy_rust::TOXIPROXY
+    ///     .enabled_proxies()
+    ///     .expect("all proxies were fetched");
+    /// ```
+    pub fn enabled_proxies(&self) -> Result<Vec<Proxy>, ToxiproxyError> {
+        let mut proxies = self
+            .all()?
+            .into_values()
+            .filter(|proxy| proxy.proxy_pack.enabled)
+            .collect::<Vec<_>>();
+        proxies.sort_by(|a, b| a.proxy_pack.name.cmp(&b.proxy_pack.name));
+
+        Ok(proxies)
+    }
+
+    /// Like [`all`], but reads each proxy on its own so that one entry of an unexpected shape (e.g.
+    /// from a newer server) doesn't make the others inaccessible. Malformed entries are reported
+    /// with their deserialization error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxies = toxiproxy_rust::TOXIPROXY.all_lenient().expect("all proxies were fetched");
+    /// assert!(proxies.malformed.is_empty());
+    /// ```
+    ///
+    /// [`all`]: #method.all
+    pub fn all_lenient(&self) -> Result<LenientProxies, ToxiproxyError> {
+        let entries = lock_client(&self.client)
+            .get("proxies")
+            .and_then(read_json::<HashMap<String, serde_json::Value>>)?;
+
+        let mut lenient = LenientProxies {
+            proxies: HashMap::new(),
+            malformed: HashMap::new(),
+        };
+        for (name, entry) in entries {
+            match serde_json::from_value::<ProxyPack>(entry) {
+                Ok(proxy_pack) => {
+                    let proxy = Proxy::new(proxy_pack, self.client.clone());
+                    lenient.proxies.insert(name, proxy);
+                }
+                Err(err) => {
+                    lenient.malformed.insert(name, err.to_string());
+                }
+            }
+        }
+
+        Ok(lenient)
+    }
+
+    /// Human readable summary of every proxy and its active toxics, e.g. to attach to the output
+    /// of a failed test:
+    ///
+    /// ```text
+    /// socket (enabled): 127.0.0.1:2001 -> localhost:2000
+    ///   latency_downstream: latency on downstream, toxicity 1, {"jitter":0,"latency":2000}
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// println!("{}", toxiproxy_rust::TOXIPROXY.fault_report().expect("report was built"));
+    /// ```
+    pub fn fault_report(&self) -> Result<String, ToxiproxyError> {
+        let mut proxies = self.all()?.into_iter().collect::<Vec<(String, Proxy)>>();
+        proxies.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        let mut report = String::new();
+        for (name, proxy) in proxies {
+            let proxy_pack = &proxy.proxy_pack;
+            report.push_str(&format!(
+                "{} ({}): {} -> {}\n",
+                name,
+                if proxy_pack.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                },
+                proxy_pack.listen,
+                proxy_pack.upstream
+            ));
+
+            if proxy_pack.toxics.is_empty() {
+                report.push_str("  no toxics\n");
+            }
+            for toxic in &proxy_pack.toxics {
+                let attributes = toxic.attributes.iter().collect::<BTreeMap<_, _>>();
+                let stream = toxic
+                    .stream
+                    .as_ref()
+                    .map(|stream| format!(" on {}", stream))
+                    .unwrap_or_default();
+                report.push_str(&format!(
+                    "  {}: {}{}, toxicity {}, {}\n",
+                    toxic.name,
+                    toxic.r#type,
+                    stream,
+                    toxic.toxicity,
+                    serde_json::to_string(&attributes).expect(ERR_JSON_SERIALIZE)
+                ));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Snapshot of the fault state in the Prometheus text format, e.g. to serve for scraping in a
+    /// long-running chaos environment:
+    ///
+    /// ```text
+    /// # HELP toxiproxy_proxy_enabled Whether the proxy accepts connections.
+    /// # TYPE toxiproxy_proxy_enabled gauge
+    /// toxiproxy_proxy_enabled{proxy="socket"} 1
+    /// # HELP toxiproxy_active_toxics Number of active toxics of a type on the proxy.
+    /// # TYPE toxiproxy_active_toxics gauge
+    /// toxiproxy_active_toxics{proxy="socket",type="latency"} 1
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let metrics = toxiproxy_rust::TOXIPROXY.export_metrics().expect("metrics were exported");
+    /// ```
+    pub fn export_metrics(&self) -> Result<String, ToxiproxyError> {
+        let proxies = self.all()?.into_iter().collect::<BTreeMap<String, Proxy>>();
+
+        let mut metrics = String::from(
+            "# HELP toxiproxy_proxy_enabled Whether the proxy accepts connections.\n\
+             # TYPE toxiproxy_proxy_enabled gauge\n",
+        );
+        for (name, proxy) in &proxies {
+            metrics.push_str(&format!(
+                "toxiproxy_proxy_enabled{{proxy=\"{}\"}} {}\n",
+                escape_label(name),
+                proxy.proxy_pack.enabled as u8
+            ));
+        }
+
+        metrics.push_str(
+            "# HELP toxiproxy_active_toxics Number of active toxics of a type on the proxy.\n\
+             # TYPE toxiproxy_active_toxics gauge\n",
+        );
+        for (name, proxy) in &proxies {
+            let mut counts = BTreeMap::new();
+            for toxic in &proxy.proxy_pack.toxics {
+                *counts.entry(toxic.r#type.as_str()).or_insert(0) += 1;
+            }
+            for (r#type, count) in counts {
+                metrics.push_str(&format!(
+                    "toxiproxy_active_toxics{{proxy=\"{}\",type=\"{}\"}} {}\n",
+                    escape_label(name),
+                    escape_label(r#type),
+                    count
+                ));
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    /// Connection metrics of every proxy, aggregated into totals. Metrics the server doesn't
+    /// provide are omitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let summary = toxiproxy_rust::TOXIPROXY.metrics_summary().expect("metrics were fetched");
+    /// ```
+    pub fn metrics_summary(&self) -> Result<MetricsSummary, ToxiproxyError> {
+        lock_client(&self.client)
+            .get("proxies")
+            .and_then(read_json::<HashMap<String, ProxyStats>>)
+            .map(|proxy_stats| {
+                MetricsSummary::new(
+                    proxy_stats
+                        .into_iter()
+                        .map(|(name, proxy_stats)| (name, proxy_stats.stats))
+                        .collect(),
+                )
+            })
+    }
+
+    /// Removes every proxy from the server, running up to `parallelism` deletions at the same time.
+    /// Useful to tear down large proxy sets quickly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// toxiproxy_rust::TOXIPROXY.delete_all_proxies(4).expect("all proxies were deleted");
+    /// ```
+    pub fn delete_all_proxies(&self, parallelism: usize) -> Result<(), ToxiproxyError> {
+        let names = self.all()?.into_keys().collect();
+
+        self.fan_out(names, parallelism, |client, name| {
+            client
+                .delete(&format!("proxies/{}", name))
+                .and_then(check_status)
+                .map(|_| ())
+        })
+    }
+
+    /// Names of the proxies on the server not in `expected`, sorted - e.g. proxies leaked by a
+    /// crashed test run. With `delete` they are removed from the server as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let leaked = toxiproxy_rust::TOXIPROXY
+    ///     .prune_orphans(&["socket"], false)
+    ///     .expect("proxies were listed");
+    /// ```
+    pub fn prune_orphans(
+        &self,
+        expected: &[&str],
+        delete: bool,
+    ) -> Result<Vec<String>, ToxiproxyError> {
+        let mut orphans = self
+            .all()?
+            .into_values()
+            .filter(|proxy| !expected.contains(&proxy.proxy_pack.name.as_str()))
+            .collect::<Vec<Proxy>>();
+        orphans.sort_by(|a, b| a.proxy_pack.name.cmp(&b.proxy_pack.name));
+
+        if delete {
+            for proxy in &orphans {
+                proxy.delete()?;
+            }
+        }
+
+        Ok(orphans
+            .into_iter()
+            .map(|proxy| proxy.proxy_pack.name)
+            .collect())
+    }
+
+    fn fan_out<F>(
+        &self,
+        names: Vec<String>,
+        parallelism: usize,
+        operation: F,
+    ) -> Result<(), ToxiproxyError>
+    where
+        F: Fn(&HttpClient, &str) -> Result<(), ToxiproxyError> + Sync,
+    {
+        // Workers use their own handle so requests don't serialize on the shared lock,
+        // the underlying connection pool is still shared.
+        let _shared = share_requests();
+        let client = lock(&self.client).clone();
+        let queue = Mutex::new(names);
+
+        std::thread::scope(|scope| {
+            let workers = (0..parallelism.max(1))
+                .map(|_| {
+                    scope.spawn(|| loop {
+                        let name = match lock(&queue).pop() {
+                            Some(name) => name,
+                            None => return Ok(()),
+                        };
+                        operation(&client, &name)?;
                     })
-                    .map_err(|err| format!("json deserialize failed: {}", err))
+                })
+                .collect::<Vec<_>>();
+
+            workers.into_iter().try_for_each(|worker| {
+                worker
+                    .join()
+                    .map_err(|_| "worker thread panicked".to_owned())?
             })
+        })
     }
 
     /// Health check for the Toxiproxy server.
@@ -131,30 +1043,73 @@ impl Client {
     /// }
     /// ```
     pub fn is_running(&self) -> bool {
-        self.client.lock().expect("Client lock failed").is_alive()
+        lock_client(&self.client).is_alive()
+    }
+
+    /// Waits until the server accepts connections, e.g. right after starting it, failing once
+    /// `timeout` passes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// toxiproxy_rust::TOXIPROXY
+    ///     .wait_until_running(Duration::from_secs(5))
+    ///     .expect("server is running");
+    /// ```
+    pub fn wait_until_running(&self, timeout: Duration) -> Result<(), ToxiproxyError> {
+        let deadline = Instant::now() + timeout;
+        while !self.is_running() {
+            if Instant::now() >= deadline {
+                return Err(format!("server is not running after {:?}", timeout).into());
+            }
+            std::thread::sleep(RUNNING_POLL_INTERVAL);
+        }
+
+        Ok(())
     }
 
-    /// Version of the Toxiproxy server.
+    /// Version of the Toxiproxy server - the raw string as sent by the server, and parsed if
+    /// it's a `major.minor.patch` version.
     ///
     /// # Examples
     ///
     /// ```
     /// let version = toxiproxy_rust::TOXIPROXY.version().expect("version is returned");
+    /// println!("{}", version.raw);
     /// ```
-    pub fn version(&self) -> Result<String, String> {
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
+    pub fn version(&self) -> Result<ServerVersion, ToxiproxyError> {
+        lock_client(&self.client)
             .get("version")
-            .map(|ref mut response| {
+            .and_then(check_status)
+            .and_then(|mut response| {
+                // Reads every chunk of a chunked response, not just the first one.
                 let mut body = String::new();
                 response
                     .read_to_string(&mut body)
-                    .expect("HTTP response cannot be read");
-                body
+                    .map_err(|err| format!("version cannot be read: {}", err))?;
+                Ok(ServerVersion::new(body))
             })
     }
 
+    /// Toxic types the server accepts, based on its version - e.g. to check a toxic type before
+    /// relying on it. See [`ServerVersion::supported_toxic_types`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let types = toxiproxy_rust::TOXIPROXY
+    ///     .supported_toxic_types()
+    ///     .expect("version is returned");
+    /// assert!(types.contains(&"latency".to_owned()));
+    /// ```
+    ///
+    /// [`ServerVersion::supported_toxic_types`]: ../version/struct.ServerVersion.html#method.supported_toxic_types
+    pub fn supported_toxic_types(&self) -> Result<Vec<String>, ToxiproxyError> {
+        self.version()
+            .map(|version| version.supported_toxic_types())
+    }
+
     /// Fetches a proxy a resets its state (remove active toxics). Usually a good way to start a test and to start setting up
     /// toxics fresh against the proxy.
     ///
@@ -168,7 +1123,7 @@ impl Client {
     /// # )]).unwrap();
     /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").expect("proxy returned");
     /// ```
-    pub fn find_and_reset_proxy(&self, name: &str) -> Result<Proxy, String> {
+    pub fn find_and_reset_proxy(&self, name: &str) -> Result<Proxy, ToxiproxyError> {
         self.find_proxy(name).and_then(|proxy| {
             proxy.delete_all_toxics()?;
             proxy.enable()?;
@@ -177,7 +1132,8 @@ impl Client {
     }
 
     /// Fetches a proxy. Useful to fetch a proxy for a test where more fine grained control is required
-    /// over a proxy and its toxics.
+    /// over a proxy and its toxics. The lookup is read-only, use [`find_and_reset_proxy`] to also
+    /// clear its toxics.
     ///
     /// # Examples
     ///
@@ -189,18 +1145,176 @@ impl Client {
     /// # )]).unwrap();
     /// let proxy = toxiproxy_rust::TOXIPROXY.find_proxy("socket").expect("proxy returned");
     /// ```
-    pub fn find_proxy(&self, name: &str) -> Result<Proxy, String> {
+    ///
+    /// [`find_and_reset_proxy`]: #method.find_and_reset_proxy
+    pub fn find_proxy(&self, name: &str) -> Result<Proxy, ToxiproxyError> {
         let path = format!("proxies/{}", name);
 
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
+        lock_client(&self.client)
             .get(&path)
-            .and_then(|response| {
-                response
-                    .json()
-                    .map_err(|err| format!("json deserialize failed: {}", err))
-            })
-            .and_then(|proxy_pack: ProxyPack| Ok(Proxy::new(proxy_pack, self.client.clone())))
+            .and_then(read_json)
+            .map(|proxy_pack: ProxyPack| Proxy::new(proxy_pack, self.client.clone()))
+    }
+
+    /// Like [`find_proxy`], but a missing proxy is `Ok(None)` instead of an error. Only the proxy
+    /// itself is fetched, and it is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]).unwrap();
+    /// if let Some(proxy) = toxiproxy_rust::TOXIPROXY.get_proxy("socket").unwrap() {
+    ///     println!("{} toxics", proxy.proxy_pack.toxics.len());
+    /// }
+    /// ```
+    ///
+    /// [`find_proxy`]: #method.find_proxy
+    pub fn get_proxy(&self, name: &str) -> Result<Option<Proxy>, ToxiproxyError> {
+        match self.find_proxy(name) {
+            Ok(proxy) => Ok(Some(proxy)),
+            Err(ToxiproxyError::Server { status: 404, .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a copy of the `source` proxy named `new_name` and listening on `new_listen`, with
+    /// the same upstream and toxics. Handy to give parallel tests their own independent proxy.
+    /// Nothing is left behind if the copy fails half-way. A missing `source` fails with a
+    /// [`ToxiproxyError::Server`] error of status 404.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]).unwrap();
+    /// let copy = toxiproxy_rust::TOXIPROXY
+    ///     .clone_proxy("socket", "socket_copy", "localhost:2012")
+    ///     .expect("proxy cloned");
+    /// # copy.delete().unwrap();
+    /// ```
+    ///
+    /// [`ToxiproxyError::Server`]: ../error/enum.ToxiproxyError.html#variant.Server
+    pub fn clone_proxy(
+        &self,
+        source: &str,
+        new_name: &str,
+        new_listen: &str,
+    ) -> Result<Proxy, ToxiproxyError> {
+        let source = self.find_proxy(source).map_err(|err| match err {
+            // Keeps the status, e.g. 404 for a missing source, so callers can still match on it.
+            ToxiproxyError::Server { status, message } => ToxiproxyError::Server {
+                status,
+                message: format!("source proxy `{}` cannot be fetched: {}", source, message),
+            },
+            err => err,
+        })?;
+        let toxics = source.toxics()?;
+
+        self.transaction(|transaction| {
+            let mut proxy_pack = ProxyPack::new(
+                new_name.into(),
+                new_listen.into(),
+                source.proxy_pack.upstream.clone(),
+            );
+            proxy_pack.enabled = source.proxy_pack.enabled;
+
+            let mut proxy = transaction.proxy(proxy_pack)?;
+            for toxic in toxics {
+                transaction.toxic(&proxy, toxic)?;
+            }
+            proxy.proxy_pack.toxics = proxy.toxics()?;
+
+            Ok(proxy)
+        })
     }
+
+    /// Returns a handle to the proxy `name` without asking the server, assuming the proxy exists.
+    /// Saves a round-trip when only toxics are to be added - operations on a missing proxy fail
+    /// when they are called. Apart from the name, the `proxy_pack` of the handle is not filled in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]).unwrap();
+    /// let proxy = toxiproxy_rust::TOXIPROXY.proxy_handle("socket");
+    /// ```
+    pub fn proxy_handle(&self, name: &str) -> Proxy {
+        Proxy::new(
+            ProxyPack::new(name.into(), String::new(), String::new()),
+            self.client.clone(),
+        )
+    }
+
+    /// Alias of [`find_proxy`], matching the naming of the [Ruby client].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]).unwrap();
+    /// let proxy = toxiproxy_rust::TOXIPROXY.get("socket").expect("proxy returned");
+    /// ```
+    ///
+    /// [`find_proxy`]: #method.find_proxy
+    /// [Ruby client]: https://github.com/Shopify/toxiproxy-ruby
+    pub fn get(&self, name: &str) -> Result<Proxy, ToxiproxyError> {
+        self.find_proxy(name)
+    }
+}
+
+/// Errors if the server left out any of the `requested` proxies from the `created` ones.
+fn check_created(requested: &[ProxyPack], created: &[ProxyPack]) -> Result<(), ToxiproxyError> {
+    let missing = requested
+        .iter()
+        .filter(|requested| !created.iter().any(|created| created.name == requested.name))
+        .map(|requested| requested.name.as_str())
+        .collect::<Vec<&str>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    Err(format!("populate has not created proxies: {}", missing.join(", ")).into())
+}
+
+/// Errors if the server bound a proxy to a different port than requested, which usually means
+/// the requested port was taken. A requested port of 0 leaves the choice to the server.
+fn check_listen(requested: &[ProxyPack], created: &ProxyPack) -> Result<(), ToxiproxyError> {
+    let requested = match requested.iter().find(|proxy| proxy.name == created.name) {
+        Some(requested) => requested,
+        None => return Ok(()),
+    };
+
+    match (requested.listen_port(), created.listen_port()) {
+        (Some(0), _) => Ok(()),
+        (Some(requested_port), Some(created_port)) if requested_port != created_port => {
+            Err(format!(
+                "proxy `{}` listens on {} instead of the requested {}",
+                created.name, created.listen, requested.listen
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
 }
@@ -1,2 +1,13 @@
-pub const ERR_LOCK: &str = "Lock cannot be granted";
+use std::time::Duration;
+
 pub const ERR_JSON_SERIALIZE: &str = "JSON serialization failed";
+pub const ERR_TOXIC_UNBOUND: &str = "Toxic is not bound to a proxy";
+pub const ERR_RESPONSE_TOO_LARGE: &str = "Response too large";
+pub const ERR_PROXY_NOT_FOUND: &str = "proxy not found";
+pub const ECHO_READ_TIMEOUT: Duration = Duration::from_secs(2);
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+pub const RUNNING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+#[cfg(feature = "docker")]
+pub const CONTAINER_START_TIMEOUT: Duration = Duration::from_secs(30);
@@ -0,0 +1,127 @@
+//! Toxiproxy server in a Docker container, started on demand by a test (behind the `docker`
+//! feature). Needs the `docker` CLI and a running daemon.
+
+use super::client::*;
+use super::consts::*;
+use super::error::*;
+use std::ops::Deref;
+use std::process::Command;
+
+/// Official Toxiproxy image.
+pub const TOXIPROXY_IMAGE: &str = "ghcr.io/shopify/toxiproxy";
+
+/// A running Toxiproxy container, removed again when dropped. Derefs to a [`Client`] connected
+/// to it.
+///
+/// [`Client`]: ../client/struct.Client.html
+pub struct ToxiproxyContainer {
+    id: String,
+    client: Client,
+}
+
+impl ToxiproxyContainer {
+    /// Starts the [official image] and waits until its API answers. Proxies listen inside the
+    /// container, so the ports of the proxies to be created are given as `proxy_ports` - each is
+    /// published on the same port of 127.0.0.1. The host is reachable from the container - e.g.
+    /// as the upstream of a proxy - under the name `host.docker.internal`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use toxiproxy_rust::{container::ToxiproxyContainer, proxy::ProxyPack};
+    ///
+    /// let toxiproxy = ToxiproxyContainer::start(&[2001]).expect("container started");
+    /// toxiproxy.populate(vec![ProxyPack::new(
+    ///     "socket".into(),
+    ///     "0.0.0.0:2001".into(),
+    ///     "host.docker.internal:2000".into(),
+    /// )]);
+    /// ```
+    ///
+    /// [official image]: https://github.com/Shopify/toxiproxy/pkgs/container/toxiproxy
+    pub fn start(proxy_ports: &[u16]) -> Result<Self, ToxiproxyError> {
+        Self::start_image(TOXIPROXY_IMAGE, proxy_ports)
+    }
+
+    /// Like [`start`], with another image, e.g. a pinned version like
+    /// `ghcr.io/shopify/toxiproxy:2.5.0`.
+    ///
+    /// [`start`]: #method.start
+    pub fn start_image(image: &str, proxy_ports: &[u16]) -> Result<Self, ToxiproxyError> {
+        let mut args = vec![
+            "run".to_owned(),
+            "--detach".to_owned(),
+            "--rm".to_owned(),
+            "--add-host=host.docker.internal:host-gateway".to_owned(),
+            "--publish=127.0.0.1::8474".to_owned(),
+        ];
+        args.extend(
+            proxy_ports
+                .iter()
+                .map(|port| format!("--publish=127.0.0.1:{}:{}", port, port)),
+        );
+        args.push(image.to_owned());
+
+        let id = docker(&args)?;
+        // From here on the container is removed on drop, also when it never gets ready.
+        let mut container = Self {
+            client: Client::new("127.0.0.1:8474"),
+            id,
+        };
+
+        let port = docker(&[
+            "port".to_owned(),
+            container.id.clone(),
+            "8474/tcp".to_owned(),
+        ])?;
+        let addr = port
+            .lines()
+            .next()
+            .ok_or_else(|| format!("container {} publishes no API port", container.id))?;
+        container.client = Client::new(addr);
+        container
+            .client
+            .wait_until_running(CONTAINER_START_TIMEOUT)?;
+
+        Ok(container)
+    }
+
+    /// Id of the container.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Deref for ToxiproxyContainer {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Drop for ToxiproxyContainer {
+    fn drop(&mut self) {
+        if let Err(err) = docker(&["rm".to_owned(), "--force".to_owned(), self.id.clone()]) {
+            log::warn!("container {} cannot be removed: {}", self.id, err);
+        }
+    }
+}
+
+/// Runs a docker command, returning its trimmed output.
+fn docker(args: &[String]) -> Result<String, ToxiproxyError> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .map_err(|err| format!("docker cannot be run: {}", err))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "docker {} has failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
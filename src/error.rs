@@ -0,0 +1,87 @@
+//! Error type of all operations of the client.
+
+use std::fmt;
+
+/// Why an operation against the Toxiproxy server has failed.
+///
+/// The `Display` output is the message previously returned as a plain `String`, e.g.
+/// `server error 404: proxy not found`.
+#[derive(Debug)]
+pub enum ToxiproxyError {
+    /// The request could not be sent, or its response not be read.
+    Http(reqwest::Error),
+    /// The server answered with an error status; `message` is the `error` of its body, or the
+    /// raw body when it has a different shape.
+    Server { status: u16, message: String },
+    /// The server could not be reached any more while cleaning up after a test closure, e.g. in
+    /// [`Proxy::apply`] - it most likely died during the test.
+    ///
+    /// [`Proxy::apply`]: ../proxy/struct.Proxy.html#method.apply
+    ServerWentAway(reqwest::Error),
+    /// A body could not be serialized or deserialized.
+    Serde(serde_json::Error),
+    /// Any other failure detected by the client itself, e.g. an invalid toxic, a proxy without
+    /// the expected toxic or a failed echo through a proxy.
+    Other(String),
+}
+
+impl fmt::Display for ToxiproxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(err) => write!(f, "http error: {}", err),
+            Self::Server { status, message } => write!(f, "server error {}: {}", status, message),
+            Self::ServerWentAway(err) => write!(f, "server went away: {}", err),
+            Self::Serde(err) => write!(f, "json error: {}", err),
+            Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ToxiproxyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(err) | Self::ServerWentAway(err) => Some(err),
+            Self::Serde(err) => Some(err),
+            Self::Server { .. } | Self::Other(_) => None,
+        }
+    }
+}
+
+impl ToxiproxyError {
+    /// Turns a failure to reach the server into [`ServerWentAway`], leaving other errors as is.
+    ///
+    /// [`ServerWentAway`]: #variant.ServerWentAway
+    pub(crate) fn server_went_away(self) -> Self {
+        match self {
+            Self::Http(err) if err.is_connect() || err.is_timeout() || err.is_request() => {
+                Self::ServerWentAway(err)
+            }
+            err => err,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ToxiproxyError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+impl From<serde_json::Error> for ToxiproxyError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Serde(err)
+    }
+}
+
+/// Eases returning ad-hoc errors, e.g. `Err("setup failed".into())` from a transaction.
+impl From<String> for ToxiproxyError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for ToxiproxyError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_owned())
+    }
+}
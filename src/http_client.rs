@@ -1,71 +1,334 @@
-use reqwest::{blocking::Client, blocking::Response, Url};
+use super::consts::*;
+use super::error::*;
+use reqwest::{blocking::Client, blocking::RequestBuilder, blocking::Response, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
 use std::{
+    cell::Cell,
+    io::Read,
     net::{SocketAddr, ToSocketAddrs},
+    ops::{Deref, DerefMut},
     str::FromStr,
+    sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard},
+    time::Duration,
 };
 
-#[derive(Debug)]
+/// Orders the requests of all clients in this process against [`exclusively`].
+static REQUESTS: RwLock<()> = RwLock::new(());
+
+thread_local! {
+    /// Set while the thread runs [`exclusively`], so that its own requests go through.
+    static EXCLUSIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Clone, Debug)]
 pub struct HttpClient {
     client: Client,
-    toxiproxy_addr: SocketAddr,
+    toxiproxy_addrs: Vec<SocketAddr>,
+    base_path: String,
+    max_response_size: Option<u64>,
+    content_type: bool,
 }
 
 impl HttpClient {
-    pub(crate) fn new<U: ToSocketAddrs>(toxiproxy_addr: U) -> Self {
+    pub(crate) fn new<U: ToSocketAddrs>(toxiproxy_addr: U) -> Result<Self, ToxiproxyError> {
+        let client = Self::build_client(DEFAULT_REQUEST_TIMEOUT, DEFAULT_CONNECT_TIMEOUT)
+            .expect("HTTP client is built");
+
+        Self::with_client(toxiproxy_addr, client)
+    }
+
+    /// A `reqwest` client giving up on a request after `request_timeout`, and on establishing
+    /// the connection after `connect_timeout`.
+    pub(crate) fn build_client(
+        request_timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Result<Client, ToxiproxyError> {
+        Ok(Client::builder()
+            .timeout(request_timeout)
+            .connect_timeout(connect_timeout)
+            .build()?)
+    }
+
+    pub(crate) fn with_client<U: ToSocketAddrs>(
+        toxiproxy_addr: U,
+        client: Client,
+    ) -> Result<Self, ToxiproxyError> {
+        Ok(Self::with_addrs(resolve(toxiproxy_addr)?, client))
+    }
+
+    fn with_addrs(toxiproxy_addrs: Vec<SocketAddr>, client: Client) -> Self {
         Self {
-            client: Client::new(),
-            toxiproxy_addr: toxiproxy_addr.to_socket_addrs().unwrap().next().unwrap(),
+            client,
+            toxiproxy_addrs,
+            base_path: String::new(),
+            max_response_size: None,
+            content_type: true,
         }
     }
 
-    pub(crate) fn get(&self, path: &str) -> Result<Response, String> {
-        self.client
-            .get(self.uri_with_path(path)?)
-            .header("Content-Type", "application/json")
-            .send()
-            .map_err(|err| format!("GET error: {}", err))
+    /// A client for another server, sending requests through the same `reqwest` client - and
+    /// so the same connection pool - as `self`.
+    pub(crate) fn with_address<U: ToSocketAddrs>(
+        &self,
+        toxiproxy_addr: U,
+    ) -> Result<Self, ToxiproxyError> {
+        Ok(Self::with_addrs(
+            resolve(toxiproxy_addr)?,
+            self.client.clone(),
+        ))
+    }
+
+    pub(crate) fn set_base_path(&mut self, base_path: &str) {
+        self.base_path = base_path.trim_matches('/').to_owned();
+    }
+
+    pub(crate) fn set_max_response_size(&mut self, max_response_size: u64) {
+        self.max_response_size = Some(max_response_size);
+    }
+
+    pub(crate) fn disable_content_type(&mut self) {
+        self.content_type = false;
+    }
+
+    pub(crate) fn get(&self, path: &str) -> Result<Response, ToxiproxyError> {
+        self.send(self.client.get(self.uri_with_path(path)?))
+    }
+
+    pub(crate) fn post(&self, path: &str) -> Result<Response, ToxiproxyError> {
+        self.send(self.client.post(self.uri_with_path(path)?))
+    }
+
+    pub(crate) fn post_with_data(
+        &self,
+        path: &str,
+        body: String,
+    ) -> Result<Response, ToxiproxyError> {
+        self.send(self.client.post(self.uri_with_path(path)?).body(body))
     }
 
-    pub(crate) fn post(&self, path: &str) -> Result<Response, String> {
-        self.client
-            .post(self.uri_with_path(path)?)
-            .header("Content-Type", "application/json")
-            .send()
-            .map_err(|err| format!("POST error: {}", err))
+    /// Like `post_with_data`, bounded by `timeout` instead of the timeout of the `reqwest` client.
+    pub(crate) fn post_with_timeout(
+        &self,
+        path: &str,
+        body: String,
+        timeout: Duration,
+    ) -> Result<Response, ToxiproxyError> {
+        self.send(
+            self.client
+                .post(self.uri_with_path(path)?)
+                .body(body)
+                .timeout(timeout),
+        )
     }
 
-    pub(crate) fn post_with_data(&self, path: &str, body: String) -> Result<Response, String> {
-        self.client
-            .post(self.uri_with_path(path)?)
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .map_err(|err| format!("POST error: {}", err))
+    pub(crate) fn delete(&self, path: &str) -> Result<Response, ToxiproxyError> {
+        self.send(self.client.delete(self.uri_with_path(path)?))
     }
 
-    pub(crate) fn delete(&self, path: &str) -> Result<Response, String> {
-        self.client
-            .delete(self.uri_with_path(path)?)
-            .header("Content-Type", "application/json")
-            .send()
-            .map_err(|err| format!("DELETE error: {}", err))
+    fn send(&self, request: RequestBuilder) -> Result<Response, ToxiproxyError> {
+        let request = if self.content_type {
+            request.header("Content-Type", "application/json")
+        } else {
+            request
+        };
+        let response = request.send()?;
+
+        match self.max_response_size {
+            Some(max_response_size) => limit_response(response, max_response_size),
+            None => Ok(response),
+        }
     }
 
-    fn uri_with_path(&self, path: &str) -> Result<Url, String> {
+    fn uri_with_path(&self, path: &str) -> Result<Url, ToxiproxyError> {
         let mut base: String = "http://".into();
-        base.push_str(&self.toxiproxy_addr.to_string());
+        base.push_str(&self.toxiproxy_addrs[0].to_string());
 
         let mut url = Url::from_str(&base).map_err(|err| format!("Incorrect address: {}", err))?;
 
         url.set_scheme("http")
             .map_err(|_| "invalid scheme".to_owned())?;
-        url.set_path(path);
+        if self.base_path.is_empty() {
+            url.set_path(path);
+        } else {
+            url.set_path(&format!("{}/{}", self.base_path, path));
+        }
         Ok(url)
     }
 
     pub(crate) fn is_alive(&self) -> bool {
-        std::net::TcpStream::connect(self.toxiproxy_addr)
+        // A hostname may resolve to several records (e.g. both IPv6 and IPv4), each is tried in turn.
+        std::net::TcpStream::connect(&self.toxiproxy_addrs[..])
             .map(|_| true)
             .unwrap_or(false)
     }
 }
+
+/// Resolves the address of the server, failing when it resolves to no address at all - requests
+/// are sent to the first one.
+pub(crate) fn resolve<U: ToSocketAddrs>(
+    toxiproxy_addr: U,
+) -> Result<Vec<SocketAddr>, ToxiproxyError> {
+    let toxiproxy_addrs = toxiproxy_addr
+        .to_socket_addrs()
+        .map_err(|err| format!("Incorrect address: {}", err))?
+        .collect::<Vec<SocketAddr>>();
+    if toxiproxy_addrs.is_empty() {
+        return Err("Incorrect address: no socket address to connect to".into());
+    }
+
+    Ok(toxiproxy_addrs)
+}
+
+/// Buffers the body of `response`, failing as soon as more than `max_response_size` bytes arrive
+/// instead of holding an arbitrarily large body in memory.
+fn limit_response(
+    mut response: Response,
+    max_response_size: u64,
+) -> Result<Response, ToxiproxyError> {
+    let too_large = || -> ToxiproxyError {
+        format!(
+            "{}: body exceeds the limit of {} bytes",
+            ERR_RESPONSE_TOO_LARGE, max_response_size
+        )
+        .into()
+    };
+    if response.content_length().unwrap_or(0) > max_response_size {
+        return Err(too_large());
+    }
+
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(response.headers().clone());
+    }
+
+    let mut body = Vec::new();
+    (&mut response)
+        .take(max_response_size + 1)
+        .read_to_end(&mut body)
+        .map_err(|err| format!("response read failed: {}", err))?;
+    if body.len() as u64 > max_response_size {
+        return Err(too_large());
+    }
+
+    builder
+        .body(body)
+        .map(Response::from)
+        .map_err(|err| format!("response rebuild failed: {}", err).into())
+}
+
+/// Error body of the server, e.g. `{"error": "proxy not found", "status": 404}`.
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Passes a successful response through. For an error status the message of the server is
+/// returned instead - reqwest itself only fails on transport errors.
+pub(crate) fn check_status(response: Response) -> Result<Response, ToxiproxyError> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(server_error(status, &body));
+    }
+
+    Ok(response)
+}
+
+/// Deserializes a successful response, see [`check_status`] for error statuses.
+pub(crate) fn read_json<T: DeserializeOwned>(response: Response) -> Result<T, ToxiproxyError> {
+    let body = check_status(response)?.bytes()?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Takes the message of a `{"error": ...}` body, falling back to the raw body when it has a
+/// different shape.
+pub(crate) fn server_error(status: StatusCode, body: &str) -> ToxiproxyError {
+    let message = serde_json::from_str::<ErrorBody>(body)
+        .map(|body| body.error)
+        .unwrap_or_else(|_| body.trim().to_owned());
+
+    ToxiproxyError::Server {
+        status: status.as_u16(),
+        message,
+    }
+}
+
+/// Locks `mutex`, also when a thread panicked while holding it. Nothing guarded by the client's
+/// mutexes is left half-updated by a panic, so one panicking test doesn't fail all others.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Locks the client behind `mutex` for a request. Waits while another thread runs
+/// [`exclusively`] - the share of [`REQUESTS`] is always taken before the client, so the two
+/// locks cannot deadlock.
+pub(crate) fn lock_client(mutex: &Mutex<HttpClient>) -> ClientGuard<'_> {
+    let shared = share_requests();
+
+    ClientGuard {
+        client: lock(mutex),
+        _shared: shared,
+    }
+}
+
+/// Share of [`REQUESTS`] for requests sent without [`lock_client`], e.g. from worker threads.
+/// `None` within [`exclusively`], which already excludes everybody else.
+pub(crate) fn share_requests() -> Option<RwLockReadGuard<'static, ()>> {
+    if EXCLUSIVE.with(Cell::get) {
+        return None;
+    }
+
+    Some(
+        REQUESTS
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    )
+}
+
+/// Runs `operation` while no other thread of this process sends a request through a client.
+/// Servers shared with other processes are not coordinated.
+pub(crate) fn exclusively<T, F: FnOnce() -> T>(operation: F) -> T {
+    if EXCLUSIVE.with(Cell::get) {
+        return operation();
+    }
+
+    /// Clears the flag again, also when `operation` panics.
+    struct Exclusive;
+    impl Drop for Exclusive {
+        fn drop(&mut self) {
+            EXCLUSIVE.with(|exclusive| exclusive.set(false));
+        }
+    }
+
+    let _requests = REQUESTS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    EXCLUSIVE.with(|exclusive| exclusive.set(true));
+    let _exclusive = Exclusive;
+
+    operation()
+}
+
+/// A locked client, see [`lock_client`].
+pub(crate) struct ClientGuard<'a> {
+    client: MutexGuard<'a, HttpClient>,
+    _shared: Option<RwLockReadGuard<'static, ()>>,
+}
+
+impl Deref for ClientGuard<'_> {
+    type Target = HttpClient;
+
+    fn deref(&self) -> &HttpClient {
+        &self.client
+    }
+}
+
+impl DerefMut for ClientGuard<'_> {
+    fn deref_mut(&mut self) -> &mut HttpClient {
+        &mut self.client
+    }
+}
@@ -39,7 +39,9 @@
 //!     .find_and_reset_proxy("socket")
 //!     .unwrap()
 //!     .with_slicer("downstream".into(), 2048, 128, 0, 0.8)
+//!     .unwrap()
 //!     .with_bandwidth("downstream".into(), 32, 0.5)
+//!     .unwrap()
 //!     .apply(|| {
 //!         /* For example:
 //!         let result = MyService::Server.call();
@@ -50,18 +52,51 @@
 //!
 //! [Toxiproxy]: https://github.com/Shopify/toxiproxy
 
-#[macro_use]
-extern crate lazy_static;
-
+#[cfg(feature = "async")]
+pub mod async_client;
 pub mod client;
 mod consts;
+#[cfg(feature = "docker")]
+pub mod container;
+pub mod error;
 mod http_client;
+pub mod metrics;
+pub mod mirror;
 pub mod proxy;
+pub mod scenario;
 pub mod toxic;
+pub mod transaction;
+pub mod version;
 
 use client::*;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+/// Address of the server used when [`TOXIPROXY_URL_ENV`] is not set.
+pub const TOXIPROXY_DEFAULT_URI: &str = "http://127.0.0.1:8474";
+
+/// Environment variable holding the address of the server used by [`TOXIPROXY`], e.g.
+/// `http://toxiproxy:8474` in CI.
+pub const TOXIPROXY_URL_ENV: &str = "TOXIPROXY_URL";
+
+/// Pre-built client using the address in [`TOXIPROXY_URL_ENV`], if set, or the default one. See
+/// [`Client::from_env`].
+pub static TOXIPROXY: DefaultClient = DefaultClient {
+    client: OnceLock::new(),
+};
+
+/// Lazily built [`Client`] behind [`TOXIPROXY`], created on first use.
+pub struct DefaultClient {
+    client: OnceLock<Client>,
+}
+
+impl Deref for DefaultClient {
+    type Target = Client;
 
-lazy_static! {
-    /// Pre-built client using the default connection address.
-    pub static ref TOXIPROXY: Client = Client::new("127.0.0.1:8474");
+    fn deref(&self) -> &Client {
+        self.client.get_or_init(|| {
+            Client::from_env()
+                .unwrap_or_else(|err| panic!("{} is invalid: {}", TOXIPROXY_URL_ENV, err))
+        })
+    }
 }
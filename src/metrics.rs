@@ -0,0 +1,43 @@
+//! Connection metrics of proxies. The stock Toxiproxy server doesn't report these - some builds
+//! add a `stats` object to each proxy, which is picked up here if present.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Metrics of a single proxy. Fields the server doesn't report are `None`.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ProxyMetrics {
+    pub connections: Option<u64>,
+    pub bytes_sent: Option<u64>,
+    pub bytes_received: Option<u64>,
+}
+
+/// Metrics of all proxies and their totals.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MetricsSummary {
+    pub proxies: HashMap<String, ProxyMetrics>,
+    pub total_connections: u64,
+    pub total_bytes_sent: u64,
+    pub total_bytes_received: u64,
+}
+
+impl MetricsSummary {
+    pub(crate) fn new(proxies: HashMap<String, ProxyMetrics>) -> Self {
+        let sum = |field: fn(&ProxyMetrics) -> Option<u64>| -> u64 {
+            proxies.values().filter_map(field).sum()
+        };
+
+        Self {
+            total_connections: sum(|metrics| metrics.connections),
+            total_bytes_sent: sum(|metrics| metrics.bytes_sent),
+            total_bytes_received: sum(|metrics| metrics.bytes_received),
+            proxies,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ProxyStats {
+    #[serde(default)]
+    pub(crate) stats: ProxyMetrics,
+}
@@ -0,0 +1,152 @@
+//! Mirrors operations to two Toxiproxy servers, e.g. when redundant backends are each fronted by
+//! their own Toxiproxy instance and should suffer the same faults.
+
+use super::client::*;
+use super::error::*;
+use super::proxy::*;
+use std::collections::HashMap;
+
+/// Pair of server clients. Mutating operations are applied to both servers, reads are served by
+/// the primary.
+#[derive(Clone)]
+pub struct MirroredClient {
+    primary: Client,
+    secondary: Client,
+}
+
+impl MirroredClient {
+    /// Creates a new mirrored client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// # use toxiproxy_rust::mirror::MirroredClient;
+    /// let client = MirroredClient::new(Client::new("127.0.0.1:8474"), Client::new("127.0.0.1:8474"));
+    /// ```
+    pub fn new(primary: Client, secondary: Client) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Establish a set of proxies on both servers. Returns the proxies of the primary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// # use toxiproxy_rust::mirror::MirroredClient;
+    /// # use toxiproxy_rust::proxy::ProxyPack;
+    /// let client = MirroredClient::new(Client::new("127.0.0.1:8474"), Client::new("127.0.0.1:8474"));
+    /// let proxies = client.populate(vec![ProxyPack::new(
+    ///     "socket".into(),
+    ///     "localhost:2001".into(),
+    ///     "localhost:2000".into(),
+    /// )]).expect("populate has completed");
+    /// ```
+    pub fn populate(&self, proxies: Vec<ProxyPack>) -> Result<Vec<Proxy>, ToxiproxyError> {
+        self.mirror(|client| client.populate(proxies.clone()))
+    }
+
+    /// Enable all proxies and remove all active toxics on both servers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// # use toxiproxy_rust::mirror::MirroredClient;
+    /// let client = MirroredClient::new(Client::new("127.0.0.1:8474"), Client::new("127.0.0.1:8474"));
+    /// client.reset().expect("reset has completed");
+    /// ```
+    pub fn reset(&self) -> Result<(), ToxiproxyError> {
+        self.mirror(|client| client.reset())
+    }
+
+    /// Fetches the named proxy from both servers and runs `operation` against each of them.
+    /// The way to install the same toxics on both servers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// # use toxiproxy_rust::mirror::MirroredClient;
+    /// # use toxiproxy_rust::proxy::ProxyPack;
+    /// let client = MirroredClient::new(Client::new("127.0.0.1:8474"), Client::new("127.0.0.1:8474"));
+    /// # client.populate(vec![ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]).unwrap();
+    /// client.with_proxy("socket", |proxy| {
+    ///     proxy.ensure_latency("downstream".into(), 2000, 0, 1.0)?;
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn with_proxy<F>(&self, name: &str, operation: F) -> Result<(), ToxiproxyError>
+    where
+        F: Fn(&Proxy) -> Result<(), ToxiproxyError>,
+    {
+        self.mirror(|client| client.find_proxy(name).and_then(|proxy| operation(&proxy)))
+    }
+
+    /// Returns all registered proxies and their toxics of the primary server.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// # use toxiproxy_rust::mirror::MirroredClient;
+    /// let client = MirroredClient::new(Client::new("127.0.0.1:8474"), Client::new("127.0.0.1:8474"));
+    /// let proxies = client.all().expect("all proxies were fetched");
+    /// ```
+    pub fn all(&self) -> Result<HashMap<String, Proxy>, ToxiproxyError> {
+        self.primary.all()
+    }
+
+    /// Fetches a proxy of the primary server.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::client::Client;
+    /// # use toxiproxy_rust::mirror::MirroredClient;
+    /// # use toxiproxy_rust::proxy::ProxyPack;
+    /// let client = MirroredClient::new(Client::new("127.0.0.1:8474"), Client::new("127.0.0.1:8474"));
+    /// # client.populate(vec![ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]).unwrap();
+    /// let proxy = client.find_proxy("socket").expect("proxy returned");
+    /// ```
+    pub fn find_proxy(&self, name: &str) -> Result<Proxy, ToxiproxyError> {
+        self.primary.find_proxy(name)
+    }
+
+    /// The primary server client.
+    pub fn primary(&self) -> &Client {
+        &self.primary
+    }
+
+    /// The secondary server client.
+    pub fn secondary(&self) -> &Client {
+        &self.secondary
+    }
+
+    /// Runs the operation against both servers - the secondary is attempted even if the primary
+    /// failed, so both end up as close to the requested state as possible.
+    fn mirror<T, F>(&self, operation: F) -> Result<T, ToxiproxyError>
+    where
+        F: Fn(&Client) -> Result<T, ToxiproxyError>,
+    {
+        match (operation(&self.primary), operation(&self.secondary)) {
+            (Ok(result), Ok(_)) => Ok(result),
+            (Err(err), Ok(_)) => Err(format!("primary error: {}", err).into()),
+            (Ok(_), Err(err)) => Err(format!("secondary error: {}", err).into()),
+            (Err(primary_err), Err(secondary_err)) => Err(format!(
+                "primary error: {}; secondary error: {}",
+                primary_err, secondary_err
+            )
+            .into()),
+        }
+    }
+}
@@ -5,20 +5,33 @@
 //! [`Toxic`]: toxic.ToxicPack.html
 
 use super::consts::*;
+use super::error::*;
 use super::http_client::*;
 use super::toxic::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
+use std::thread::spawn;
+use std::time::{Duration, Instant};
 
 /// Raw info about a Proxy.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProxyPack {
     pub name: String,
     pub listen: String,
     pub upstream: String,
     pub enabled: bool,
     pub toxics: Vec<ToxicPack>,
+    /// Seed for the randomness of the toxics (jitter, toxicity), for reproducible runs. Only
+    /// sent when set. Servers without per proxy seeds ignore it - upstream Toxiproxy only takes
+    /// a server wide `-seed` flag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rand_seed: Option<i64>,
 }
 
 impl ProxyPack {
@@ -40,20 +53,190 @@ impl ProxyPack {
             upstream,
             enabled: true,
             toxics: vec![],
+            rand_seed: None,
         }
     }
+
+    /// Requests a seed for the randomness of the toxics, see [`rand_seed`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let proxy_pack = toxiproxy_rust::proxy::ProxyPack::new(
+    ///     "socket".into(),
+    ///     "localhost:2001".into(),
+    ///     "localhost:2000".into(),
+    /// )
+    /// .with_seed(42);
+    /// ```
+    ///
+    /// [`rand_seed`]: #structfield.rand_seed
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.rand_seed = Some(seed);
+        self
+    }
+
+    pub(crate) fn listen_port(&self) -> Option<u16> {
+        self.listen.rsplit(':').next()?.parse().ok()
+    }
+
+    /// Creates the proxy on the server, failing if one with the same name exists.
+    pub(crate) fn create(&self, client: &HttpClient) -> Result<ProxyPack, ToxiproxyError> {
+        let body = serde_json::to_string(self).expect(ERR_JSON_SERIALIZE);
+
+        client
+            .post_with_data("proxies", body)
+            .and_then(read_json::<ProxyPack>)
+    }
+
+    /// Whether the proxy `active` on the server is configured as requested by `self`.
+    pub(crate) fn same_config(&self, active: &ProxyPack) -> bool {
+        let same_listen = self.listen == active.listen
+            || match self.listen_port() {
+                Some(0) => true,
+                Some(port) => active.listen_port() == Some(port),
+                None => false,
+            };
+
+        self.name == active.name
+            && self.upstream == active.upstream
+            && self.enabled == active.enabled
+            && same_listen
+    }
 }
 
 /// Client handler of the Proxy object.
-#[derive(Debug)]
 pub struct Proxy {
     pub proxy_pack: ProxyPack,
     client: Arc<Mutex<HttpClient>>,
+    default_toxicity: f32,
+    labels: BTreeMap<String, String>,
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("proxy_pack", &self.proxy_pack)
+            .field("client", &format_args!("<client>"))
+            .field("default_toxicity", &self.default_toxicity)
+            .field("labels", &self.labels)
+            .finish()
+    }
 }
 
 impl Proxy {
-    pub(crate) fn new(proxy_pack: ProxyPack, client: Arc<Mutex<HttpClient>>) -> Self {
-        Self { proxy_pack, client }
+    /// Wraps a proxy fetched from the server. Its toxics are bound to the client as well, so they
+    /// can be updated straight away.
+    pub(crate) fn new(mut proxy_pack: ProxyPack, client: Arc<Mutex<HttpClient>>) -> Self {
+        let name = &proxy_pack.name;
+        let toxics = proxy_pack
+            .toxics
+            .drain(..)
+            .map(|toxic| toxic.with_client(name, client.clone()))
+            .collect();
+        proxy_pack.toxics = toxics;
+
+        Self {
+            proxy_pack,
+            client,
+            default_toxicity: 1.0,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the toxicity used by the `*_default` toxic registrations, such as
+    /// [`with_latency_default`]. Defaults to `1.0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let mut proxy = toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap();
+    /// proxy.set_default_toxicity(0.5);
+    /// ```
+    ///
+    /// [`with_latency_default`]: #method.with_latency_default
+    pub fn set_default_toxicity(&mut self, toxicity: f32) {
+        self.default_toxicity = toxicity;
+    }
+
+    /// Returns the toxicity used when a toxic is registered without one.
+    pub fn default_toxicity(&self) -> f32 {
+        self.default_toxicity
+    }
+
+    /// Tags the handle with a label, e.g. `service=payments`, for the caller's own bookkeeping.
+    /// Labels are kept on this handle only - the server has no notion of them, so a handle
+    /// fetched again starts without any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let mut proxy = toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap();
+    /// proxy.set_label("service", "payments");
+    /// assert!(proxy.has_label("service", "payments"));
+    /// ```
+    pub fn set_label(&mut self, key: &str, value: &str) {
+        self.labels.insert(key.into(), value.into());
+    }
+
+    /// Value of the label `key`, if set.
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+
+    /// Whether the label `key` is set to `value` - e.g. to filter handles by label.
+    pub fn has_label(&self, key: &str, value: &str) -> bool {
+        self.label(key) == Some(value)
+    }
+
+    /// All labels of the handle.
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// Parses the address the proxy listens on. A host-less `:port` form, as accepted by the
+    /// server, resolves to `127.0.0.1`; host names are resolved to their first address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let addr = toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .listen_addr()
+    ///   .unwrap();
+    /// assert_eq!(2001, addr.port());
+    /// ```
+    pub fn listen_addr(&self) -> Result<SocketAddr, ToxiproxyError> {
+        let listen = &self.proxy_pack.listen;
+        let listen = match listen.strip_prefix(':') {
+            Some(port) => format!("127.0.0.1:{}", port),
+            None => listen.clone(),
+        };
+
+        if let Ok(addr) = listen.parse::<SocketAddr>() {
+            return Ok(addr);
+        }
+        listen
+            .to_socket_addrs()
+            .map_err(|err| format!("invalid listen address `{}`: {}", listen, err))?
+            .next()
+            .ok_or_else(|| format!("listen address `{}` does not resolve", listen).into())
     }
 
     /// Disables the proxy - making all connections running through them fail immediately.
@@ -68,10 +251,10 @@ impl Proxy {
     /// # )]);
     /// toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap().disable();
     /// ```
-    pub fn disable(&self) -> Result<(), String> {
+    pub fn disable(&self) -> Result<(), ToxiproxyError> {
         let mut payload: HashMap<String, bool> = HashMap::new();
         payload.insert("enabled".into(), false);
-        let body = serde_json::to_string(&payload).map_err(|_| ERR_JSON_SERIALIZE)?;
+        let body = serde_json::to_string(&payload)?;
 
         self.update(body)
     }
@@ -88,21 +271,62 @@ impl Proxy {
     /// # )]);
     /// toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap().enable();
     /// ```
-    pub fn enable(&self) -> Result<(), String> {
+    pub fn enable(&self) -> Result<(), ToxiproxyError> {
         let mut payload: HashMap<String, bool> = HashMap::new();
         payload.insert("enabled".into(), true);
-        let body = serde_json::to_string(&payload).map_err(|_| ERR_JSON_SERIALIZE)?;
+        let body = serde_json::to_string(&payload)?;
 
         self.update(body)
     }
 
-    fn update(&self, payload: String) -> Result<(), String> {
+    /// Changes the given fields of the proxy on the server - fields left `None` keep their
+    /// value - and refreshes them in `proxy_pack` from the answer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut proxy = toxiproxy_rust::TOXIPROXY
+    ///     .create_proxy_unique("moving", "127.0.0.1:0", "localhost:2000")
+    ///     .unwrap();
+    /// proxy.update_fields(None, Some("localhost:2002"), None).unwrap();
+    /// assert_eq!("localhost:2002", proxy.proxy_pack.upstream);
+    /// # proxy.delete().unwrap();
+    /// ```
+    pub fn update_fields(
+        &mut self,
+        listen: Option<&str>,
+        upstream: Option<&str>,
+        enabled: Option<bool>,
+    ) -> Result<(), ToxiproxyError> {
+        let mut payload = serde_json::Map::new();
+        if let Some(listen) = listen {
+            payload.insert("listen".into(), listen.into());
+        }
+        if let Some(upstream) = upstream {
+            payload.insert("upstream".into(), upstream.into());
+        }
+        if let Some(enabled) = enabled {
+            payload.insert("enabled".into(), enabled.into());
+        }
+        let body = serde_json::to_string(&payload)?;
+        let path = format!("proxies/{}", self.proxy_pack.name);
+
+        let updated: ProxyPack = lock_client(&self.client)
+            .post_with_data(&path, body)
+            .and_then(read_json)?;
+        self.proxy_pack.listen = updated.listen;
+        self.proxy_pack.upstream = updated.upstream;
+        self.proxy_pack.enabled = updated.enabled;
+
+        Ok(())
+    }
+
+    fn update(&self, payload: String) -> Result<(), ToxiproxyError> {
         let path = format!("proxies/{}", self.proxy_pack.name);
 
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
+        lock_client(&self.client)
             .post_with_data(&path, payload)
+            .and_then(check_status)
             .map(|_| ())
     }
 
@@ -118,17 +342,21 @@ impl Proxy {
     /// # )]);
     /// toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap().delete();
     /// ```
-    pub fn delete(&self) -> Result<(), String> {
+    pub fn delete(&self) -> Result<(), ToxiproxyError> {
         let path = format!("proxies/{}", self.proxy_pack.name);
 
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
+        lock_client(&self.client)
             .delete(&path)
+            .and_then(check_status)
             .map(|_| ())
     }
 
-    /// Retrieve all toxics registered on the proxy.
+    /// Retrieve all toxics registered on the proxy, in the order the server reports them.
+    ///
+    /// The server chains the toxics of a stream in the order they were added - data passes the
+    /// first added toxic first. So a `slicer` added before a `latency` slices the data and then
+    /// delays every slice, while the reverse delays the data before slicing it. See
+    /// [`toxics_on_stream`] for the chain of a single stream.
     ///
     /// # Examples
     ///
@@ -140,21 +368,24 @@ impl Proxy {
     /// # )]);
     /// let toxics = toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap().toxics().unwrap();
     /// ```
-    pub fn toxics(&self) -> Result<Vec<ToxicPack>, String> {
+    ///
+    /// [`toxics_on_stream`]: #method.toxics_on_stream
+    pub fn toxics(&self) -> Result<Vec<ToxicPack>, ToxiproxyError> {
         let path = format!("proxies/{}/toxics", self.proxy_pack.name);
 
-        self.client
-            .lock()
-            .map_err(|err| format!("lock error: {}", err))?
+        lock_client(&self.client)
             .get(&path)
-            .and_then(|response| {
-                response
-                    .json()
-                    .map_err(|err| format!("json deserialize failed: {}", err))
+            .and_then(read_json::<Vec<ToxicPack>>)
+            .map(|toxics| {
+                toxics
+                    .into_iter()
+                    .map(|toxic| toxic.with_client(&self.proxy_pack.name, self.client.clone()))
+                    .collect()
             })
     }
 
-    /// Registers a [latency] Toxic.
+    /// Retrieve a single toxic by name, bound to the proxy like the ones of [`toxics`]. Returns
+    /// `None` when the proxy has no such toxic - but fails when the proxy itself doesn't exist.
     ///
     /// # Examples
     ///
@@ -164,33 +395,32 @@ impl Proxy {
     /// #    "localhost:2001".into(),
     /// #    "localhost:2000".into(),
     /// # )]);
-    /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
-    ///   .unwrap()
-    ///   .with_latency("downstream".into(), 2000, 0, 1.0);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxic = proxy.get_toxic("latency_downstream").unwrap();
+    /// assert!(toxic.is_none());
     /// ```
     ///
-    /// [latency]: https://github.com/Shopify/toxiproxy#latency
-    pub fn with_latency(
-        &self,
-        stream: String,
-        latency: ToxicValueType,
-        jitter: ToxicValueType,
-        toxicity: f32,
-    ) -> &Self {
-        let mut attributes = HashMap::new();
-        attributes.insert("latency".into(), latency);
-        attributes.insert("jitter".into(), jitter);
+    /// [`toxics`]: #method.toxics
+    pub fn get_toxic(&self, name: &str) -> Result<Option<ToxicPack>, ToxiproxyError> {
+        let path = format!("proxies/{}/toxics/{}", self.proxy_pack.name, name);
 
-        self.create_toxic(ToxicPack::new(
-            "latency".into(),
-            stream,
-            toxicity,
-            attributes,
-        ))
+        match lock_client(&self.client)
+            .get(&path)
+            .and_then(read_json::<ToxicPack>)
+        {
+            Ok(toxic) => Ok(Some(
+                toxic.with_client(&self.proxy_pack.name, self.client.clone()),
+            )),
+            Err(ToxiproxyError::Server {
+                status: 404,
+                message,
+            }) if message != ERR_PROXY_NOT_FOUND => Ok(None),
+            Err(err) => Err(err),
+        }
     }
 
-    /// Registers a [bandwith] Toxic.
+    /// Retrieve the toxics of one stream (`upstream` or `downstream`) in the order they are
+    /// applied to passing data.
     ///
     /// # Examples
     ///
@@ -200,55 +430,86 @@ impl Proxy {
     /// #    "localhost:2001".into(),
     /// #    "localhost:2000".into(),
     /// # )]);
-    /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
-    ///   .unwrap()
-    ///   .with_bandwidth("downstream".into(), 500, 1.0);
+    /// let chain = toxiproxy_rust::TOXIPROXY
+    ///     .find_proxy("socket")
+    ///     .unwrap()
+    ///     .toxics_on_stream("downstream")
+    ///     .unwrap();
     /// ```
-    ///
-    /// [bandwith]: https://github.com/Shopify/toxiproxy#bandwith
-    pub fn with_bandwidth(&self, stream: String, rate: ToxicValueType, toxicity: f32) -> &Self {
-        let mut attributes = HashMap::new();
-        attributes.insert("rate".into(), rate);
-
-        self.create_toxic(ToxicPack::new(
-            "bandwidth".into(),
-            stream,
-            toxicity,
-            attributes,
-        ))
+    pub fn toxics_on_stream(&self, stream: &str) -> Result<Vec<ToxicPack>, ToxiproxyError> {
+        self.toxics().map(|toxics| {
+            toxics
+                .into_iter()
+                .filter(|toxic| toxic.stream.as_deref() == Some(stream))
+                .collect()
+        })
     }
 
-    /// Registers a [slow_close] Toxic.
+    /// Compares the active toxics with the `expected` ones, e.g. to assert that a setup took
+    /// effect. Attributes left out of an expected toxic are not compared.
     ///
     /// # Examples
     ///
     /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
     /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
     /// #    "socket".into(),
     /// #    "localhost:2001".into(),
     /// #    "localhost:2000".into(),
     /// # )]);
-    /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
-    ///   .unwrap()
-    ///   .with_slow_close("downstream".into(), 500, 1.0);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let expected = ToxicBuilder::new("latency".into())
+    ///     .attribute("latency".into(), 2000)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let diff = proxy.diff_toxics(&[expected]).unwrap();
+    /// assert_eq!(1, diff.missing.len());
     /// ```
+    pub fn diff_toxics(&self, expected: &[ToxicPack]) -> Result<ToxicDiff, ToxiproxyError> {
+        self.toxics().map(|active| ToxicDiff::new(expected, active))
+    }
+
+    /// Polls the toxics of the proxy every `interval` on a background thread and calls `callback`
+    /// with the new set whenever toxics were added, removed or changed - also by other clients.
     ///
-    /// [slow_close]: https://github.com/Shopify/toxiproxy#slow_close
-    pub fn with_slow_close(&self, stream: String, delay: ToxicValueType, toxicity: f32) -> &Self {
-        let mut attributes = HashMap::new();
-        attributes.insert("delay".into(), delay);
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let watch = toxiproxy_rust::TOXIPROXY
+    ///     .find_proxy("socket")
+    ///     .unwrap()
+    ///     .watch_toxics(std::time::Duration::from_millis(500), |toxics| {
+    ///         println!("{} toxics active", toxics.len());
+    ///     })
+    ///     .unwrap();
+    /// watch.stop();
+    /// ```
+    pub fn watch_toxics<C>(
+        &self,
+        interval: Duration,
+        callback: C,
+    ) -> Result<ToxicWatch, ToxiproxyError>
+    where
+        C: FnMut(&[ToxicPack]) + Send + 'static,
+    {
+        let proxy = Proxy::new(self.proxy_pack.clone(), self.client.clone());
 
-        self.create_toxic(ToxicPack::new(
-            "slow_close".into(),
-            stream,
-            toxicity,
-            attributes,
+        Ok(ToxicWatch::new(
+            self.toxics()?,
+            interval,
+            move || proxy.toxics(),
+            callback,
         ))
     }
 
-    /// Registers a [timeout] Toxic.
+    /// Freshly fetched state of the proxy and its toxics as pretty printed JSON. Handy to include
+    /// in the failure output of a test.
     ///
     /// # Examples
     ///
@@ -258,26 +519,30 @@ impl Proxy {
     /// #    "localhost:2001".into(),
     /// #    "localhost:2000".into(),
     /// # )]);
-    /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
-    ///   .unwrap()
-    ///   .with_timeout("downstream".into(), 5000, 1.0);
+    /// let snapshot = toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap().debug_snapshot().unwrap();
     /// ```
-    ///
-    /// [timeout]: https://github.com/Shopify/toxiproxy#timeout
-    pub fn with_timeout(&self, stream: String, timeout: ToxicValueType, toxicity: f32) -> &Self {
-        let mut attributes = HashMap::new();
-        attributes.insert("timeout".into(), timeout);
+    pub fn debug_snapshot(&self) -> Result<String, ToxiproxyError> {
+        let proxy_pack = self.fetch()?;
+        let toxic_count = proxy_pack.toxics.len();
 
-        self.create_toxic(ToxicPack::new(
-            "timeout".into(),
-            stream,
-            toxicity,
-            attributes,
-        ))
+        let mut snapshot = serde_json::to_value(&proxy_pack)?;
+        snapshot["toxic_count"] = toxic_count.into();
+
+        Ok(serde_json::to_string_pretty(&snapshot)?)
+    }
+
+    fn fetch(&self) -> Result<ProxyPack, ToxiproxyError> {
+        let path = format!("proxies/{}", self.proxy_pack.name);
+
+        lock_client(&self.client).get(&path).and_then(read_json)
     }
 
-    /// Registers a [slicer] Toxic.
+    /// Registers a [latency] Toxic.
+    ///
+    /// The toxic is named `latency_{stream}`, so a second call for the same stream collides with
+    /// the first one: the server rejects it and an error is returned. Use [`ensure_latency`] or
+    /// [`replace_toxic`] to overwrite an existing toxic on purpose, and
+    /// [`with_latency_and_drain`] to keep open connections from escaping the toxic.
     ///
     /// # Examples
     ///
@@ -290,32 +555,26 @@ impl Proxy {
     /// toxiproxy_rust::TOXIPROXY
     ///   .find_proxy("socket")
     ///   .unwrap()
-    ///   .with_slicer("downstream".into(), 1024, 128, 500, 1.0);
+    ///   .with_latency("downstream".into(), 2000, 0, 1.0)
+    ///   .unwrap();
     /// ```
     ///
-    /// [slicer]: https://github.com/Shopify/toxiproxy#slicer
-    pub fn with_slicer(
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    /// [`ensure_latency`]: #method.ensure_latency
+    /// [`replace_toxic`]: #method.replace_toxic
+    /// [`with_latency_and_drain`]: #method.with_latency_and_drain
+    pub fn with_latency(
         &self,
         stream: String,
-        average_size: ToxicValueType,
-        size_variation: ToxicValueType,
-        delay: ToxicValueType,
+        latency: ToxicValueType,
+        jitter: ToxicValueType,
         toxicity: f32,
-    ) -> &Self {
-        let mut attributes = HashMap::new();
-        attributes.insert("average_size".into(), average_size);
-        attributes.insert("size_variation".into(), size_variation);
-        attributes.insert("delay".into(), delay);
-
-        self.create_toxic(ToxicPack::new(
-            "slicer".into(),
-            stream,
-            toxicity,
-            attributes,
-        ))
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(latency_toxic(stream, latency, jitter, toxicity))
+            .map(|_| self)
     }
 
-    /// Registers a [limit_data] Toxic.
+    /// Registers a [latency] Toxic using the proxy's [default toxicity].
     ///
     /// # Examples
     ///
@@ -325,42 +584,64 @@ impl Proxy {
     /// #    "localhost:2001".into(),
     /// #    "localhost:2000".into(),
     /// # )]);
-    /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
-    ///   .unwrap()
-    ///   .with_limit_data("downstream".into(), 2048, 1.0);
+    /// let mut proxy = toxiproxy_rust::TOXIPROXY.find_proxy("socket").unwrap();
+    /// proxy.set_default_toxicity(0.5);
+    /// proxy.with_latency_default("downstream".into(), 2000, 0).unwrap();
     /// ```
     ///
-    /// [limit_data]: https://github.com/Shopify/toxiproxy#limit_data
-    pub fn with_limit_data(&self, stream: String, bytes: ToxicValueType, toxicity: f32) -> &Self {
-        let mut attributes = HashMap::new();
-        attributes.insert("bytes".into(), bytes);
-
-        self.create_toxic(ToxicPack::new(
-            "limit_data".into(),
-            stream,
-            toxicity,
-            attributes,
-        ))
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    /// [default toxicity]: #method.set_default_toxicity
+    pub fn with_latency_default(
+        &self,
+        stream: String,
+        latency: ToxicValueType,
+        jitter: ToxicValueType,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.with_latency(stream, latency, jitter, self.default_toxicity)
     }
 
-    fn create_toxic(&self, toxic: ToxicPack) -> &Self {
-        let body = serde_json::to_string(&toxic).expect(ERR_JSON_SERIALIZE);
-        let path = format!("proxies/{}/toxics", self.proxy_pack.name);
-
-        let _ = self
-            .client
-            .lock()
-            .expect(ERR_LOCK)
-            .post_with_data(&path, body)
-            .map_err(|err| {
-                panic!("<proxies>.<toxics> creation has failed: {}", err);
-            });
+    /// Registers a [latency] Toxic that is removed in the background after `duration`.
+    /// The returned handle can cancel the removal or wait for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let transient = toxiproxy_rust::TOXIPROXY
+    ///   .find_and_reset_proxy("socket")
+    ///   .unwrap()
+    ///   .with_latency_for("downstream".into(), 2000, 0, 1.0, std::time::Duration::from_millis(100))
+    ///   .unwrap();
+    /// transient.join().expect("toxic was removed");
+    /// ```
+    ///
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    pub fn with_latency_for(
+        &self,
+        stream: String,
+        latency: ToxicValueType,
+        jitter: ToxicValueType,
+        toxicity: f32,
+        duration: Duration,
+    ) -> Result<TransientToxic, ToxiproxyError> {
+        let toxic = self.add_toxic(latency_toxic(stream, latency, jitter, toxicity))?;
 
-        self
+        Ok(TransientToxic::new(
+            self.proxy_pack.name.clone(),
+            toxic.name,
+            self.client.clone(),
+            duration,
+        ))
     }
 
-    /// Runs a call as if the proxy was [disabled].
+    /// Registers a [latency] Toxic, then closes all open connections of the proxy with a brief
+    /// disable/enable cycle. A toxic is only certain to affect connections established after it
+    /// was added - depending on the server, open ones may pass through unaffected - so a client
+    /// reconnecting after this call sees the latency right away.
     ///
     /// # Examples
     ///
@@ -371,28 +652,31 @@ impl Proxy {
     /// #    "localhost:2000".into(),
     /// # )]);
     /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
+    ///   .find_and_reset_proxy("socket")
     ///   .unwrap()
-    ///   .with_down(|| {
-    ///     /* Example test:
-    ///        let service_result = MyService::Server::call(params);
-    ///        assert!(service_result.is_err());
-    ///     */
-    ///   });
+    ///   .with_latency_and_drain("downstream".into(), 2000, 0, 1.0)
+    ///   .unwrap();
     /// ```
     ///
-    /// [disabled]: https://github.com/Shopify/toxiproxy#down
-    pub fn with_down<F>(&self, closure: F) -> Result<(), String>
-    where
-        F: FnOnce(),
-    {
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    pub fn with_latency_and_drain(
+        &self,
+        stream: String,
+        latency: ToxicValueType,
+        jitter: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.with_latency(stream, latency, jitter, toxicity)?;
         self.disable()?;
-        closure();
-        self.enable()
+        self.enable()?;
+
+        Ok(self)
     }
 
-    /// Runs a call with the current Toxic setup for the proxy.
-    /// It restores proxy state after the call.
+    /// Registers differently configured [latency] Toxics on both streams, e.g. fast requests but
+    /// slow responses. `upstream` and `downstream` are each `(latency, jitter, toxicity)`; the
+    /// toxics are named `latency_upstream` and `latency_downstream`. When the downstream one
+    /// cannot be registered, the upstream one is removed again.
     ///
     /// # Examples
     ///
@@ -403,28 +687,39 @@ impl Proxy {
     /// #    "localhost:2000".into(),
     /// # )]);
     /// toxiproxy_rust::TOXIPROXY
-    ///   .find_proxy("socket")
+    ///   .find_and_reset_proxy("socket")
     ///   .unwrap()
-    ///   .with_limit_data("downstream".into(), 2048, 1.0)
-    ///   .apply(|| {
-    ///     /* Example test:
-    ///        let service_result = MyService::Server::call(giant_payload);
-    ///        assert!(service_result.is_err());
-    ///
-    ///        let service_result = MyService::Server::call(small_payload);
-    ///        assert!(service_result.is_ok());
-    ///     */
-    ///   });
+    ///   .with_latency_asymmetric((10, 0, 1.0), (2000, 100, 1.0))
+    ///   .unwrap();
     /// ```
-    pub fn apply<F>(&self, closure: F) -> Result<(), String>
-    where
-        F: FnOnce(),
-    {
-        closure();
-        self.delete_all_toxics()
+    ///
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    pub fn with_latency_asymmetric(
+        &self,
+        upstream: (ToxicValueType, ToxicValueType, f32),
+        downstream: (ToxicValueType, ToxicValueType, f32),
+    ) -> Result<&Self, ToxiproxyError> {
+        let (latency, jitter, toxicity) = upstream;
+        let upstream =
+            self.add_toxic(latency_toxic("upstream".into(), latency, jitter, toxicity))?;
+        let (latency, jitter, toxicity) = downstream;
+        if let Err(err) = self.with_latency("downstream".into(), latency, jitter, toxicity) {
+            // Best effort, so it's both streams or neither - the downstream error is what counts.
+            if let Err(cleanup) = self.remove_toxic(&upstream) {
+                log::warn!(
+                    "toxic `{}` of proxy `{}` cannot be removed: {}",
+                    upstream.name,
+                    self.proxy_pack.name,
+                    cleanup
+                );
+            }
+            return Err(err);
+        }
+
+        Ok(self)
     }
 
-    /// Deletes all toxics on the proxy.
+    /// Registers a [bandwith] Toxic. The rate is in KB/s.
     ///
     /// # Examples
     ///
@@ -437,19 +732,1176 @@ impl Proxy {
     /// toxiproxy_rust::TOXIPROXY
     ///   .find_proxy("socket")
     ///   .unwrap()
-    ///   .delete_all_toxics();
+    ///   .with_bandwidth("downstream".into(), 500, 1.0)
+    ///   .unwrap();
     /// ```
-    pub fn delete_all_toxics(&self) -> Result<(), String> {
-        self.toxics().and_then(|toxic_list| {
-            for toxic in toxic_list {
-                let path = format!("proxies/{}/toxics/{}", self.proxy_pack.name, toxic.name);
-                self.client
-                    .lock()
-                    .map_err(|err| format!("lock error: {}", err))?
-                    .delete(&path)?;
-            }
-
-            Ok(())
+    ///
+    /// [bandwith]: https://github.com/Shopify/toxiproxy#bandwith
+    pub fn with_bandwidth(
+        &self,
+        stream: String,
+        rate: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(bandwidth_toxic(stream, rate, toxicity))
+            .map(|_| self)
+    }
+
+    /// Registers a [slow_close] Toxic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_slow_close("downstream".into(), 500, 1.0)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [slow_close]: https://github.com/Shopify/toxiproxy#slow_close
+    pub fn with_slow_close(
+        &self,
+        stream: String,
+        delay: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(slow_close_toxic(stream, delay, toxicity))
+            .map(|_| self)
+    }
+
+    /// Registers a [timeout] Toxic. A `timeout` of 0 never responds and holds the connection open
+    /// until the toxic is removed, otherwise the connection is closed after `timeout` milliseconds.
+    ///
+    /// A `toxicity` below 1.0 only affects that share of the connections - a warning is logged
+    /// in that case as it's rarely what a full outage simulation wants.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_timeout("downstream".into(), 5000, 1.0)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [timeout]: https://github.com/Shopify/toxiproxy#timeout
+    pub fn with_timeout(
+        &self,
+        stream: String,
+        timeout: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(timeout_toxic(stream, timeout, toxicity))
+            .map(|_| self)
+    }
+
+    /// Registers a [slicer] Toxic, slicing data into chunks of around `average_size` bytes,
+    /// give or take `size_variation`, sent `delay` microseconds apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_slicer("downstream".into(), 1024, 128, 500, 1.0)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [slicer]: https://github.com/Shopify/toxiproxy#slicer
+    pub fn with_slicer(
+        &self,
+        stream: String,
+        average_size: ToxicValueType,
+        size_variation: ToxicValueType,
+        delay: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(slicer_toxic(
+            stream,
+            average_size,
+            size_variation,
+            delay,
+            toxicity,
+        ))
+        .map(|_| self)
+    }
+
+    /// Registers a [limit_data] Toxic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_limit_data("downstream".into(), 2048, 1.0)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [limit_data]: https://github.com/Shopify/toxiproxy#limit_data
+    pub fn with_limit_data(
+        &self,
+        stream: String,
+        bytes: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(limit_data_toxic(stream, bytes, toxicity))
+            .map(|_| self)
+    }
+
+    /// Registers a [reset_peer] Toxic: the connection is reset (TCP RST) `timeout` milliseconds
+    /// after data arrives, or right away with a `timeout` of 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_reset_peer("upstream".into(), 500, 1.0)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [reset_peer]: https://github.com/Shopify/toxiproxy#reset_peer
+    pub fn with_reset_peer(
+        &self,
+        stream: String,
+        timeout: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.add_toxic(reset_peer_toxic(stream, timeout, toxicity))
+            .map(|_| self)
+    }
+
+    /// Registers any toxic, e.g. one built with [`ToxicBuilder`], and returns it as created by the
+    /// server - with the name it was assigned - ready to be updated or removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxic = proxy
+    ///   .add_toxic(ToxicBuilder::new("reset_peer".into()).attribute("timeout".into(), 0).build().unwrap())
+    ///   .unwrap();
+    /// assert_eq!("reset_peer_downstream", toxic.name);
+    /// # proxy.delete_all_toxics().unwrap();
+    /// ```
+    ///
+    /// [`ToxicBuilder`]: ../toxic/struct.ToxicBuilder.html
+    pub fn add_toxic(&self, toxic: ToxicPack) -> Result<ToxicPack, ToxiproxyError> {
+        self.post_toxic(toxic, None)
+    }
+
+    /// Like [`add_toxic`], but the toxic is removed again when the returned guard is dropped.
+    ///
+    /// The `with_*` builders keep returning the proxy so they can be chained. To scope a toxic
+    /// of theirs to a block, pass it here instead, or guard a toxic returned by the server with
+    /// [`ToxicGuard::try_from`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// {
+    ///     let _guard = proxy
+    ///       .add_toxic_guarded(ToxicBuilder::new("reset_peer".into()).attribute("timeout".into(), 0).build().unwrap())
+    ///       .unwrap();
+    ///     /* connections are reset here */
+    /// }
+    /// ```
+    ///
+    /// [`add_toxic`]: #method.add_toxic
+    /// [`ToxicGuard::try_from`]: ../toxic/struct.ToxicGuard.html#impl-TryFrom%3CToxicPack%3E
+    pub fn add_toxic_guarded(&self, toxic: ToxicPack) -> Result<ToxicGuard, ToxiproxyError> {
+        self.add_toxic(toxic).and_then(ToxicGuard::try_from)
+    }
+
+    /// Registers a toxic, replacing the one of the same name if the proxy already has it - where
+    /// [`add_toxic`] fails instead. Returns the toxic as stored by the server.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let latency = |latency| {
+    ///     ToxicBuilder::new("latency".into()).attribute("latency".into(), latency).build().unwrap()
+    /// };
+    /// proxy.add_toxic(latency(1000)).unwrap();
+    /// assert!(proxy.add_toxic(latency(2000)).is_err());
+    /// proxy.replace_toxic(latency(2000)).unwrap();
+    /// # proxy.delete_all_toxics().unwrap();
+    /// ```
+    ///
+    /// [`add_toxic`]: #method.add_toxic
+    pub fn replace_toxic(&self, toxic: ToxicPack) -> Result<ToxicPack, ToxiproxyError> {
+        let exists = self
+            .toxics()?
+            .iter()
+            .any(|active| active.name == toxic.name);
+        if !exists {
+            return self.add_toxic(toxic);
+        }
+
+        let body = serde_json::to_string(&toxic).expect(ERR_JSON_SERIALIZE);
+        let path = format!("proxies/{}/toxics/{}", self.proxy_pack.name, toxic.name);
+
+        let toxic = lock_client(&self.client)
+            .post_with_data(&path, body)
+            .and_then(read_json::<ToxicPack>)?;
+        toxic.trace("updated", &self.proxy_pack.name);
+
+        Ok(toxic.with_client(&self.proxy_pack.name, self.client.clone()))
+    }
+
+    /// Like [`add_toxic`], but the request fails once `timeout` passes - regardless of the timeout
+    /// of the client - e.g. to bound a single install on a server under heavy load.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxic = ToxicBuilder::new("latency".into()).attribute("latency".into(), 1000).build().unwrap();
+    /// proxy.add_toxic_with_timeout(toxic, Duration::from_secs(1)).unwrap();
+    /// # proxy.delete_all_toxics().unwrap();
+    /// ```
+    ///
+    /// [`add_toxic`]: #method.add_toxic
+    pub fn add_toxic_with_timeout(
+        &self,
+        toxic: ToxicPack,
+        timeout: Duration,
+    ) -> Result<ToxicPack, ToxiproxyError> {
+        self.post_toxic(toxic, Some(timeout))
+    }
+
+    fn post_toxic(
+        &self,
+        toxic: ToxicPack,
+        timeout: Option<Duration>,
+    ) -> Result<ToxicPack, ToxiproxyError> {
+        if !self.proxy_pack.enabled {
+            // Legal, but the toxic never triggers while the proxy refuses connections.
+            log::warn!(
+                "toxic `{}` is added to proxy `{}` which is disabled",
+                toxic.name,
+                self.proxy_pack.name
+            );
+        }
+        if toxic.r#type == "timeout" && toxic.toxicity < 1.0 {
+            let timeout = toxic.integer_attribute("timeout").unwrap_or(0);
+            log::warn!(
+                "timeout toxic on proxy `{}` has a toxicity of {}: only that share of connections will {}",
+                self.proxy_pack.name,
+                toxic.toxicity,
+                if timeout == 0 {
+                    "hang".to_owned()
+                } else {
+                    format!("be closed after {}ms", timeout)
+                }
+            );
+        }
+
+        let body = serde_json::to_string(&toxic).expect(ERR_JSON_SERIALIZE);
+        let path = format!("proxies/{}/toxics", self.proxy_pack.name);
+
+        let client = lock_client(&self.client);
+        let toxic = match timeout {
+            Some(timeout) => client.post_with_timeout(&path, body, timeout),
+            None => client.post_with_data(&path, body),
+        }
+        .and_then(read_json::<ToxicPack>)?;
+        toxic.trace("created", &self.proxy_pack.name);
+
+        Ok(toxic.with_client(&self.proxy_pack.name, self.client.clone()))
+    }
+
+    /// Parses a toxic spec like `downstream latency=500 jitter=50 @0.8` and registers the toxic,
+    /// see [`ToxicBuilder::from_spec`] for the format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let spec = std::env::var("SOCKET_TOXIC").unwrap_or("latency=500 @0.8".into());
+    /// proxy.apply_spec(&spec).unwrap();
+    /// # proxy.delete_all_toxics().unwrap();
+    /// ```
+    ///
+    /// [`ToxicBuilder::from_spec`]: ../toxic/struct.ToxicBuilder.html#method.from_spec
+    pub fn apply_spec(&self, spec: &str) -> Result<ToxicPack, ToxiproxyError> {
+        self.add_toxic(ToxicBuilder::from_spec(spec)?.build()?)
+    }
+
+    /// Registers a Toxic of any [type], matching the `toxic` method of the [Ruby client].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let mut attributes = std::collections::HashMap::new();
+    /// attributes.insert("latency".into(), 2000);
+    ///
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .toxic("latency".into(), "downstream".into(), 1.0, attributes)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [type]: https://github.com/Shopify/toxiproxy#toxics
+    /// [Ruby client]: https://github.com/Shopify/toxiproxy-ruby
+    pub fn toxic<V: Into<serde_json::Value>>(
+        &self,
+        r#type: String,
+        stream: String,
+        toxicity: f32,
+        attributes: HashMap<String, V>,
+    ) -> Result<&Self, ToxiproxyError> {
+        let toxic = ToxicBuilder::new(r#type)
+            .stream(stream)
+            .toxicity(toxicity)
+            .attributes(attributes)
+            .build()?;
+
+        self.add_toxic(toxic).map(|_| self)
+    }
+
+    /// Registers a [latency] Toxic, or updates it if the proxy already has one on the same stream.
+    /// Safe to call repeatedly, e.g. at the start of every test run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .ensure_latency("downstream".into(), 2000, 0, 1.0)
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    pub fn ensure_latency(
+        &self,
+        stream: String,
+        latency: ToxicValueType,
+        jitter: ToxicValueType,
+        toxicity: f32,
+    ) -> Result<&Self, ToxiproxyError> {
+        self.ensure_toxic(latency_toxic(stream, latency, jitter, toxicity))
+            .map(|_| self)
+    }
+
+    fn ensure_toxic(&self, toxic: ToxicPack) -> Result<(), ToxiproxyError> {
+        match self.get_toxic(&toxic.name)? {
+            Some(_) => self.update_toxic(toxic),
+            None => self.add_toxic(toxic).map(|_| ()),
+        }
+    }
+
+    fn update_toxic(&self, toxic: ToxicPack) -> Result<(), ToxiproxyError> {
+        let body = serde_json::to_string(&toxic)?;
+        let path = format!("proxies/{}/toxics/{}", self.proxy_pack.name, toxic.name);
+
+        lock_client(&self.client)
+            .post_with_data(&path, body)
+            .and_then(check_status)?;
+        toxic.trace("updated", &self.proxy_pack.name);
+
+        Ok(())
+    }
+
+    /// Runs a call as if the proxy was [disabled].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_down(|| {
+    ///     /* Example test:
+    ///        let service_result = MyService::Server::call(params);
+    ///        assert!(service_result.is_err());
+    ///     */
+    ///   });
+    /// ```
+    ///
+    /// [disabled]: https://github.com/Shopify/toxiproxy#down
+    pub fn with_down<F>(&self, closure: F) -> Result<(), ToxiproxyError>
+    where
+        F: FnOnce(),
+    {
+        self.disable()?;
+        closure();
+        self.enable()
+    }
+
+    /// Runs a call while the backend is completely down: the proxy is disabled, so connections to
+    /// it are refused. Unlike [`with_down`], the proxy is enabled again also when the call
+    /// panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_and_reset_proxy("socket")
+    ///   .unwrap()
+    ///   .simulate_outage(|| {
+    ///     /* Example test:
+    ///        let service_result = MyService::Server::call(params);
+    ///        assert!(service_result.is_err());
+    ///     */
+    ///   })
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [`with_down`]: #method.with_down
+    pub fn simulate_outage<F>(&self, closure: F) -> Result<(), ToxiproxyError>
+    where
+        F: FnOnce(),
+    {
+        self.disable()?;
+        let outcome = catch_unwind(AssertUnwindSafe(closure));
+        let restored = self.enable();
+
+        if let Err(panic) = outcome {
+            resume_unwind(panic);
+        }
+        restored
+    }
+
+    /// Runs a call while the backend hangs: [timeout] Toxics without a timeout on both streams
+    /// accept connections but never pass any data. The toxics are removed again afterwards, also
+    /// when the call panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_and_reset_proxy("socket")
+    ///   .unwrap()
+    ///   .simulate_timeout(|| {
+    ///     /* Example test:
+    ///        let service_result = MyService::Server::call(params);
+    ///        assert!(service_result.is_err());
+    ///     */
+    ///   })
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [timeout]: https://github.com/Shopify/toxiproxy#timeout
+    pub fn simulate_timeout<F>(&self, closure: F) -> Result<(), ToxiproxyError>
+    where
+        F: FnOnce(),
+    {
+        let mut toxics = Vec::new();
+        for stream in &["upstream", "downstream"] {
+            let toxic = timeout_toxic((*stream).into(), 0, 1.0);
+            match self.add_toxic(toxic) {
+                Ok(toxic) => toxics.push(toxic),
+                Err(err) => {
+                    toxics
+                        .iter()
+                        .try_for_each(|toxic| self.remove_toxic(toxic))?;
+                    return Err(err);
+                }
+            }
+        }
+
+        let outcome = catch_unwind(AssertUnwindSafe(closure));
+        let restored = toxics.iter().try_for_each(|toxic| self.remove_toxic(toxic));
+
+        if let Err(panic) = outcome {
+            resume_unwind(panic);
+        }
+        restored
+    }
+
+    /// Disables the proxy, runs `configure` to install toxics and enables the proxy again. No
+    /// connection passes the proxy before the full set of toxics is in place: when `configure`
+    /// fails, its error is returned and the proxy stays disabled. When it panics, the proxy is
+    /// enabled again before the panic is resumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_and_reset_proxy("socket")
+    ///   .unwrap()
+    ///   .configure_then_enable(|proxy| {
+    ///     proxy
+    ///       .with_latency("downstream".into(), 2000, 0, 1.0)?
+    ///       .with_bandwidth("upstream".into(), 500, 1.0)?;
+    ///     Ok(())
+    ///   })
+    ///   .unwrap();
+    /// # toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket");
+    /// ```
+    pub fn configure_then_enable<F>(&self, configure: F) -> Result<(), ToxiproxyError>
+    where
+        F: FnOnce(&Self) -> Result<(), ToxiproxyError>,
+    {
+        self.disable()?;
+        match catch_unwind(AssertUnwindSafe(|| configure(self))) {
+            Ok(configured) => configured.and_then(|_| self.enable()),
+            Err(panic) => {
+                let _ = self.enable();
+                resume_unwind(panic)
+            }
+        }
+    }
+
+    /// Alias of [`with_down`], matching the naming of the [Ruby client].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .down(|| {
+    ///     /* Example test:
+    ///        let service_result = MyService::Server::call(params);
+    ///        assert!(service_result.is_err());
+    ///     */
+    ///   });
+    /// ```
+    ///
+    /// [`with_down`]: #method.with_down
+    /// [Ruby client]: https://github.com/Shopify/toxiproxy-ruby
+    pub fn down<F>(&self, closure: F) -> Result<(), ToxiproxyError>
+    where
+        F: FnOnce(),
+    {
+        self.with_down(closure)
+    }
+
+    /// Runs a call with the current Toxic setup for the proxy.
+    /// It restores proxy state after the call. When the server cannot be reached for that any
+    /// more, a [`ServerWentAway`] error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .with_limit_data("downstream".into(), 2048, 1.0)
+    ///   .unwrap()
+    ///   .apply(|| {
+    ///     /* Example test:
+    ///        let service_result = MyService::Server::call(giant_payload);
+    ///        assert!(service_result.is_err());
+    ///
+    ///        let service_result = MyService::Server::call(small_payload);
+    ///        assert!(service_result.is_ok());
+    ///     */
+    ///   });
+    /// ```
+    ///
+    /// [`ServerWentAway`]: ../error/enum.ToxiproxyError.html#variant.ServerWentAway
+    pub fn apply<F>(&self, closure: F) -> Result<(), ToxiproxyError>
+    where
+        F: FnOnce(),
+    {
+        closure();
+        self.delete_all_toxics()
+            .map_err(ToxiproxyError::server_went_away)
+    }
+
+    /// Sends `payload` through the proxy and returns the bytes that came back, assuming the
+    /// upstream echoes its input. Combined with toxics like `limit_data` or `slicer` it shows
+    /// exactly how many bytes survived the trip.
+    ///
+    /// Reading stops at EOF, on a reset connection, or after a read timeout of
+    /// two seconds, so a `timeout` toxic returns what was received instead of hanging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let echoed = toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .echo_roundtrip(b"ping");
+    /// ```
+    pub fn echo_roundtrip(&self, payload: &[u8]) -> Result<Vec<u8>, ToxiproxyError> {
+        let mut stream = TcpStream::connect(&self.proxy_pack.listen)
+            .map_err(|err| format!("connection to {} failed: {}", self.proxy_pack.listen, err))?;
+        stream
+            .set_read_timeout(Some(ECHO_READ_TIMEOUT))
+            .map_err(|err| format!("<proxies>.<echo> setup has failed: {}", err))?;
+        stream
+            .write_all(payload)
+            .and_then(|_| stream.shutdown(Shutdown::Write))
+            .map_err(|err| format!("<proxies>.<echo> write has failed: {}", err))?;
+
+        let mut echoed = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => echoed.extend_from_slice(&buffer[..read]),
+                Err(err) => match err.kind() {
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ConnectionReset => {
+                        break
+                    }
+                    ErrorKind::Interrupted => continue,
+                    _ => return Err(format!("<proxies>.<echo> read has failed: {}", err).into()),
+                },
+            }
+        }
+
+        Ok(echoed)
+    }
+
+    /// Checks whether the upstream can be reached through the proxy, with all toxics lifted for
+    /// the duration of the probe. Tells a fault injected by a toxic apart from an upstream that
+    /// is down. The toxics are reinstalled afterwards, also when the probe fails.
+    ///
+    /// The proxy closes a connection right away when it cannot dial the upstream, so a connection
+    /// which stays open for a moment counts as reachable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let reachable = toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .probe_upstream();
+    /// ```
+    pub fn probe_upstream(&self) -> Result<bool, ToxiproxyError> {
+        let toxics = self.toxics()?;
+        // Only the toxics actually lifted are reinstalled, when lifting fails half-way.
+        let mut lifted = Vec::new();
+        let probe = toxics
+            .iter()
+            .try_for_each(|toxic| -> Result<(), ToxiproxyError> {
+                self.remove_toxic(toxic)?;
+                lifted.push(toxic.clone());
+                Ok(())
+            })
+            .map(|_| self.probe_connection());
+        let restored = self.restore_toxics(&lifted);
+
+        let reachable = probe?;
+        restored.map(|_| reachable)
+    }
+
+    fn probe_connection(&self) -> bool {
+        let mut stream = match TcpStream::connect(&self.proxy_pack.listen) {
+            Ok(stream) => stream,
+            Err(_) => return false,
+        };
+        if stream.set_read_timeout(Some(PROBE_TIMEOUT)).is_err() {
+            return false;
+        }
+
+        match stream.read(&mut [0; 1]) {
+            Ok(0) => false,
+            Ok(_) => true,
+            Err(err) => matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut),
+        }
+    }
+
+    /// Checks that a [latency] toxic on `stream` actually slows traffic down: the round trip of a
+    /// byte through the proxy must be within `tolerance_ms` of `expected_ms`. Assumes the
+    /// upstream echoes its input, and fails if the proxy has no latency toxic on `stream`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let slowed_down = proxy
+    ///   .with_latency("downstream".into(), 300, 0, 1.0)
+    ///   .unwrap()
+    ///   .verify_latency_effect("downstream", 300, 100);
+    /// # proxy.delete_all_toxics().unwrap();
+    /// ```
+    ///
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    pub fn verify_latency_effect(
+        &self,
+        stream: &str,
+        expected_ms: u64,
+        tolerance_ms: u64,
+    ) -> Result<bool, ToxiproxyError> {
+        let installed = self
+            .toxics_on_stream(stream)?
+            .iter()
+            .any(|toxic| toxic.r#type == "latency");
+        if !installed {
+            return Err(format!(
+                "proxy `{}` has no latency toxic on {}",
+                self.proxy_pack.name, stream
+            )
+            .into());
+        }
+
+        let timeout = Duration::from_millis(expected_ms + tolerance_ms) + ECHO_READ_TIMEOUT;
+        let round_trip = self.measure_round_trip(timeout)?.as_millis() as u64;
+
+        Ok(round_trip.abs_diff(expected_ms) <= tolerance_ms)
+    }
+
+    /// Checks that a [bandwidth] toxic on `stream` actually throttles traffic: about a second's
+    /// worth of data at `expected_kbps` is echoed through the proxy, and the measured throughput
+    /// must be within `tolerance_kbps` of `expected_kbps`. Assumes the upstream echoes its input,
+    /// and fails if the proxy has no bandwidth toxic on `stream`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let throttled = proxy
+    ///   .with_bandwidth("downstream".into(), 100, 1.0)
+    ///   .unwrap()
+    ///   .verify_bandwidth_effect("downstream", 100, 20);
+    /// # proxy.delete_all_toxics().unwrap();
+    /// ```
+    ///
+    /// [bandwidth]: https://github.com/Shopify/toxiproxy#bandwidth
+    pub fn verify_bandwidth_effect(
+        &self,
+        stream: &str,
+        expected_kbps: u64,
+        tolerance_kbps: u64,
+    ) -> Result<bool, ToxiproxyError> {
+        let installed = self
+            .toxics_on_stream(stream)?
+            .iter()
+            .any(|toxic| toxic.r#type == "bandwidth");
+        if !installed {
+            return Err(format!(
+                "proxy `{}` has no bandwidth toxic on {}",
+                self.proxy_pack.name, stream
+            )
+            .into());
+        }
+        if expected_kbps == 0 {
+            return Err("the expected rate must be above 0 KB/s".into());
+        }
+
+        let payload_len = expected_kbps as usize * 1000;
+        let elapsed = self.measure_transfer(payload_len)?.as_secs_f64();
+        let measured_kbps = payload_len as f64 / 1000.0 / elapsed;
+
+        Ok((measured_kbps - expected_kbps as f64).abs() <= tolerance_kbps as f64)
+    }
+
+    /// Time it takes `payload_len` bytes to come back through the proxy. Written from another
+    /// thread, so that a slow reader doesn't block the writer once the socket buffers are full.
+    fn measure_transfer(&self, payload_len: usize) -> Result<Duration, ToxiproxyError> {
+        let mut stream = TcpStream::connect(&self.proxy_pack.listen)
+            .map_err(|err| format!("connection to {} failed: {}", self.proxy_pack.listen, err))?;
+        stream
+            .set_read_timeout(Some(ECHO_READ_TIMEOUT))
+            .map_err(|err| format!("<proxies>.<transfer> setup has failed: {}", err))?;
+        let mut writer = stream
+            .try_clone()
+            .map_err(|err| format!("<proxies>.<transfer> setup has failed: {}", err))?;
+
+        let start = Instant::now();
+        let written = spawn(move || writer.write_all(&vec![0; payload_len]));
+        let mut received = 0;
+        let mut buffer = [0; 8192];
+        while received < payload_len {
+            match stream.read(&mut buffer) {
+                Ok(0) => {
+                    return Err("<proxies>.<transfer> connection closed before the echo".into())
+                }
+                Ok(read) => received += read,
+                Err(err) => {
+                    return Err(format!("<proxies>.<transfer> read has failed: {}", err).into())
+                }
+            }
+        }
+        let elapsed = start.elapsed();
+
+        written
+            .join()
+            .map_err(|_| "<proxies>.<transfer> writer thread panicked".to_owned())?
+            .map_err(|err| format!("<proxies>.<transfer> write has failed: {}", err))?;
+        Ok(elapsed)
+    }
+
+    /// Time it takes a single byte to come back through the proxy.
+    fn measure_round_trip(&self, timeout: Duration) -> Result<Duration, ToxiproxyError> {
+        let mut stream = TcpStream::connect(&self.proxy_pack.listen)
+            .map_err(|err| format!("connection to {} failed: {}", self.proxy_pack.listen, err))?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|err| format!("<proxies>.<round_trip> setup has failed: {}", err))?;
+
+        let start = Instant::now();
+        stream
+            .write_all(&[0])
+            .map_err(|err| format!("<proxies>.<round_trip> write has failed: {}", err))?;
+        match stream.read(&mut [0; 1]) {
+            Ok(0) => Err("<proxies>.<round_trip> connection closed before the echo".into()),
+            Ok(_) => Ok(start.elapsed()),
+            Err(err) => Err(format!("<proxies>.<round_trip> read has failed: {}", err).into()),
+        }
+    }
+
+    /// Reinstalls all of `toxics`, also past the ones which fail - the failures are returned
+    /// together.
+    fn restore_toxics(&self, toxics: &[ToxicPack]) -> Result<(), ToxiproxyError> {
+        let path = format!("proxies/{}/toxics", self.proxy_pack.name);
+        let client = lock_client(&self.client);
+
+        let failures = toxics
+            .iter()
+            .filter_map(|toxic| {
+                let body = serde_json::to_string(toxic).expect(ERR_JSON_SERIALIZE);
+                client
+                    .post_with_data(&path, body)
+                    .and_then(read_json::<ToxicPack>)
+                    .err()
+                    .map(|err| format!("toxic `{}` cannot be restored: {}", toxic.name, err))
+            })
+            .collect::<Vec<String>>();
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        Err(failures.join("; ").into())
+    }
+
+    /// Sets the toxicity of every toxic on the proxy, e.g. to dial the faults up uniformly.
+    /// Returns how many toxics were updated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let updated = toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .set_all_toxicity(1.0)
+    ///   .unwrap();
+    /// ```
+    pub fn set_all_toxicity(&self, toxicity: f32) -> Result<usize, ToxiproxyError> {
+        let toxics = self.toxics()?;
+        for toxic in &toxics {
+            toxic.set_toxicity(toxicity)?;
+        }
+
+        Ok(toxics.len())
+    }
+
+    /// Deletes all toxics on the proxy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .delete_all_toxics();
+    /// ```
+    pub fn delete_all_toxics(&self) -> Result<(), ToxiproxyError> {
+        self.toxics().and_then(|toxic_list| {
+            for toxic in toxic_list {
+                self.remove_toxic(&toxic)?;
+            }
+
+            Ok(())
         })
     }
+
+    /// Deletes all toxics of the given [type] on the proxy, leaving other toxics in place.
+    /// Returns the number of removed toxics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let removed = toxiproxy_rust::TOXIPROXY
+    ///   .find_proxy("socket")
+    ///   .unwrap()
+    ///   .remove_toxics_of_type("latency")
+    ///   .unwrap();
+    /// ```
+    ///
+    /// [type]: https://github.com/Shopify/toxiproxy#toxics
+    pub fn remove_toxics_of_type(&self, toxic_type: &str) -> Result<usize, ToxiproxyError> {
+        let mut removed = 0;
+
+        for toxic in self.toxics()? {
+            if toxic.r#type == toxic_type {
+                self.remove_toxic(&toxic)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes the [latency] Toxic of `stream`, failing if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// toxiproxy_rust::TOXIPROXY
+    ///   .find_and_reset_proxy("socket")
+    ///   .unwrap()
+    ///   .with_latency("downstream".into(), 2000, 0, 1.0)
+    ///   .unwrap()
+    ///   .remove_latency("downstream")
+    ///   .expect("latency was removed");
+    /// ```
+    ///
+    /// [latency]: https://github.com/Shopify/toxiproxy#latency
+    pub fn remove_latency(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("latency", stream)
+    }
+
+    /// Removes the bandwidth Toxic of `stream`, failing if there is none.
+    pub fn remove_bandwidth(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("bandwidth", stream)
+    }
+
+    /// Removes the slow close Toxic of `stream`, failing if there is none.
+    pub fn remove_slow_close(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("slow_close", stream)
+    }
+
+    /// Removes the timeout Toxic of `stream`, failing if there is none.
+    pub fn remove_timeout(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("timeout", stream)
+    }
+
+    /// Removes the slicer Toxic of `stream`, failing if there is none.
+    pub fn remove_slicer(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("slicer", stream)
+    }
+
+    /// Removes the limit data Toxic of `stream`, failing if there is none.
+    pub fn remove_limit_data(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("limit_data", stream)
+    }
+
+    /// Removes the reset peer Toxic of `stream`, failing if there is none.
+    pub fn remove_reset_peer(&self, stream: &str) -> Result<(), ToxiproxyError> {
+        self.remove_toxic_of_stream("reset_peer", stream)
+    }
+
+    /// Removes the toxic under the `{type}_{stream}` name the `with_*` methods register it with.
+    fn remove_toxic_of_stream(&self, toxic_type: &str, stream: &str) -> Result<(), ToxiproxyError> {
+        let name = format!("{}_{}", toxic_type, stream);
+
+        match self.toxics()?.iter().find(|toxic| toxic.name == name) {
+            Some(toxic) => self.remove_toxic(toxic),
+            None => Err(format!("proxy `{}` has no `{}` toxic", self.proxy_pack.name, name).into()),
+        }
+    }
+
+    fn remove_toxic(&self, toxic: &ToxicPack) -> Result<(), ToxiproxyError> {
+        let path = format!("proxies/{}/toxics/{}", self.proxy_pack.name, toxic.name);
+
+        lock_client(&self.client)
+            .delete(&path)
+            .and_then(check_status)?;
+        toxic.trace("removed", &self.proxy_pack.name);
+
+        Ok(())
+    }
+}
+
+fn latency_toxic(
+    stream: String,
+    latency: ToxicValueType,
+    jitter: ToxicValueType,
+    toxicity: f32,
+) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("latency".into(), latency.into());
+    attributes.insert("jitter".into(), jitter.into());
+
+    ToxicPack::new("latency".into(), stream, toxicity, attributes)
+}
+
+fn bandwidth_toxic(stream: String, rate: ToxicValueType, toxicity: f32) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("rate".into(), rate.into());
+
+    ToxicPack::new("bandwidth".into(), stream, toxicity, attributes)
+}
+
+fn slow_close_toxic(stream: String, delay: ToxicValueType, toxicity: f32) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("delay".into(), delay.into());
+
+    ToxicPack::new("slow_close".into(), stream, toxicity, attributes)
+}
+
+fn timeout_toxic(stream: String, timeout: ToxicValueType, toxicity: f32) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("timeout".into(), timeout.into());
+
+    ToxicPack::new("timeout".into(), stream, toxicity, attributes)
+}
+
+fn slicer_toxic(
+    stream: String,
+    average_size: ToxicValueType,
+    size_variation: ToxicValueType,
+    delay: ToxicValueType,
+    toxicity: f32,
+) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("average_size".into(), average_size.into());
+    attributes.insert("size_variation".into(), size_variation.into());
+    attributes.insert("delay".into(), delay.into());
+
+    ToxicPack::new("slicer".into(), stream, toxicity, attributes)
+}
+
+fn limit_data_toxic(stream: String, bytes: ToxicValueType, toxicity: f32) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("bytes".into(), bytes.into());
+
+    ToxicPack::new("limit_data".into(), stream, toxicity, attributes)
+}
+
+fn reset_peer_toxic(stream: String, timeout: ToxicValueType, toxicity: f32) -> ToxicPack {
+    let mut attributes = HashMap::new();
+    attributes.insert("timeout".into(), timeout.into());
+
+    ToxicPack::new("reset_peer".into(), stream, toxicity, attributes)
 }
@@ -0,0 +1,53 @@
+//! Reusable, named sets of faults - e.g. a "slow-network" or "flaky-db" scenario - which are
+//! installed with [`Client::apply_scenario`] and removed again with [`Client::clear_scenario`].
+//!
+//! [`Client::apply_scenario`]: ../client/struct.Client.html#method.apply_scenario
+//! [`Client::clear_scenario`]: ../client/struct.Client.html#method.clear_scenario
+
+use super::toxic::*;
+use std::collections::BTreeMap;
+
+/// Toxics to install per proxy, under a name.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    toxics: BTreeMap<String, Vec<ToxicPack>>,
+}
+
+impl Scenario {
+    /// Creates an empty scenario.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use toxiproxy_rust::{scenario::Scenario, toxic::ToxicBuilder};
+    ///
+    /// let scenario = Scenario::new("slow-network").with_toxic(
+    ///     "socket",
+    ///     ToxicBuilder::new("latency".into())
+    ///         .attribute("latency".into(), 1000)
+    ///         .build()
+    ///         .unwrap(),
+    /// );
+    /// ```
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            toxics: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a toxic to install on the proxy named `proxy_name`.
+    pub fn with_toxic(mut self, proxy_name: &str, toxic: ToxicPack) -> Self {
+        self.toxics
+            .entry(proxy_name.into())
+            .or_default()
+            .push(toxic);
+        self
+    }
+
+    /// The toxics of the scenario, by proxy name.
+    pub fn toxics(&self) -> &BTreeMap<String, Vec<ToxicPack>> {
+        &self.toxics
+    }
+}
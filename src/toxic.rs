@@ -2,19 +2,115 @@
 //!
 //! [Toxic]: https://github.com/Shopify/toxiproxy#toxics
 
+use super::consts::*;
+use super::error::*;
+use super::http_client::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
 
-pub type ToxicValueType = u32;
+/// Integer attribute of the built-in toxics, signed 64 bit as on the server - a slicer `delay`
+/// (in microseconds) or a limit_data `bytes` may exceed `u32::MAX`.
+pub type ToxicValueType = i64;
+
+/// Attributes of a Toxic. The built-in toxics only take integers, but plugin toxics may use any
+/// JSON value.
+pub type ToxicAttributes = HashMap<String, Value>;
 
 /// Config of a Toxic.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "RawToxicPack")]
 pub struct ToxicPack {
+    /// Name as reported by the server. Toxics created by other clients (the CLI, the Ruby gem)
+    /// may use any name, not only the `{type}_{stream}` this client generates.
     pub name: String,
     pub r#type: String,
-    pub stream: String,
+    /// `upstream` or `downstream`. Left out for custom toxics which don't apply to a stream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<String>,
     pub toxicity: f32,
-    pub attributes: HashMap<String, ToxicValueType>,
+    pub attributes: ToxicAttributes,
+    #[serde(skip)]
+    proxy_name: Option<String>,
+    #[serde(skip)]
+    client: Option<Arc<Mutex<HttpClient>>>,
+}
+
+/// Toxic as sent by the server, before its attributes are normalized.
+#[derive(Deserialize)]
+struct RawToxicPack {
+    name: String,
+    r#type: String,
+    #[serde(default)]
+    stream: Option<String>,
+    toxicity: f32,
+    attributes: ToxicAttributes,
+}
+
+impl TryFrom<RawToxicPack> for ToxicPack {
+    type Error = String;
+
+    /// Integer-valued floats (e.g. `2000.0`) become integers and fractional values are rejected,
+    /// for the attributes of the known toxic types only - which are all integers. Any other
+    /// attribute is kept as sent.
+    fn try_from(raw: RawToxicPack) -> Result<Self, String> {
+        let schema = attribute_schema(&raw.r#type);
+        let mut attributes = ToxicAttributes::with_capacity(raw.attributes.len());
+
+        for (key, value) in raw.attributes {
+            let integer = schema.is_some_and(|(required, optional)| {
+                required.contains(&key.as_str()) || optional.contains(&key.as_str())
+            });
+            let value = match value.as_f64() {
+                Some(_) if !integer || !value.is_f64() => value,
+                Some(number) if number.fract() == 0.0 => {
+                    if number >= 0.0 {
+                        Value::from(number as u64)
+                    } else {
+                        Value::from(number as i64)
+                    }
+                }
+                Some(number) => {
+                    return Err(format!(
+                        "{} toxic expects an integer for `{}`, got {}",
+                        raw.r#type, key, number
+                    ))
+                }
+                _ => value,
+            };
+            attributes.insert(key, value);
+        }
+
+        let mut toxic = ToxicPack::with_stream(raw.r#type, raw.stream, raw.toxicity, attributes);
+        toxic.name = raw.name;
+        Ok(toxic)
+    }
+}
+
+impl fmt::Debug for ToxicPack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ToxicPack");
+        debug
+            .field("name", &self.name)
+            .field("type", &self.r#type)
+            .field("stream", &self.stream)
+            .field("toxicity", &self.toxicity)
+            .field("attributes", &self.attributes);
+
+        if let Some(proxy_name) = &self.proxy_name {
+            debug
+                .field("proxy_name", proxy_name)
+                .field("client", &format_args!("<client>"));
+        }
+
+        debug.finish()
+    }
 }
 
 impl ToxicPack {
@@ -22,15 +118,682 @@ impl ToxicPack {
         r#type: String,
         stream: String,
         toxicity: f32,
-        attributes: HashMap<String, ToxicValueType>,
+        attributes: ToxicAttributes,
+    ) -> Self {
+        Self::with_stream(r#type, Some(stream), toxicity, attributes)
+    }
+
+    /// Named `{type}_{stream}`, or just `{type}` without a stream.
+    fn with_stream(
+        r#type: String,
+        stream: Option<String>,
+        toxicity: f32,
+        attributes: ToxicAttributes,
     ) -> Self {
-        let name = format!("{}_{}", r#type, stream);
+        let name = match &stream {
+            Some(stream) => format!("{}_{}", r#type, stream),
+            None => r#type.clone(),
+        };
         Self {
             name,
             r#type,
             stream,
             toxicity,
             attributes,
+            proxy_name: None,
+            client: None,
+        }
+    }
+
+    /// Type, stream and toxicity are equal and all attributes of `self` have the same value on
+    /// `other`. Attributes the server filled in with defaults are ignored that way.
+    fn matches(&self, other: &ToxicPack) -> bool {
+        self.r#type == other.r#type
+            && self.stream == other.stream
+            && self.toxicity == other.toxicity
+            && self
+                .attributes
+                .iter()
+                .all(|(key, value)| other.attributes.get(key) == Some(value))
+    }
+
+    pub(crate) fn with_client(mut self, proxy_name: &str, client: Arc<Mutex<HttpClient>>) -> Self {
+        self.proxy_name = Some(proxy_name.into());
+        self.client = Some(client);
+        self
+    }
+
+    /// Integer value of an attribute, `None` if it is missing or of another type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// let toxic = ToxicBuilder::new("latency".into())
+    ///     .attribute("latency".into(), 2000)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(Some(2000), toxic.integer_attribute("latency"));
+    /// assert_eq!(None, toxic.integer_attribute("jitter"));
+    /// ```
+    pub fn integer_attribute(&self, key: &str) -> Option<ToxicValueType> {
+        self.attributes.get(key)?.as_i64()
+    }
+
+    /// Numeric value of an attribute - integers included - for plugin toxics taking fractions.
+    pub fn float_attribute(&self, key: &str) -> Option<f64> {
+        self.attributes.get(key)?.as_f64()
+    }
+
+    /// String value of an attribute, for plugin toxics.
+    pub fn string_attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key)?.as_str()
+    }
+
+    /// Sets the `latency` attribute (in milliseconds) of the toxic on the server and returns the
+    /// refreshed toxic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxics = proxy.with_latency("downstream".into(), 2000, 0, 1.0).unwrap().toxics().unwrap();
+    /// let toxic = toxics[0].set_latency(1000).unwrap();
+    /// ```
+    pub fn set_latency(&self, latency: ToxicValueType) -> Result<ToxicPack, ToxiproxyError> {
+        self.set_attribute("latency", latency)
+    }
+
+    /// Sets the `jitter` attribute (in milliseconds) of the toxic on the server and returns the
+    /// refreshed toxic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxics = proxy.with_latency("downstream".into(), 2000, 0, 1.0).unwrap().toxics().unwrap();
+    /// let toxic = toxics[0].set_jitter(100).unwrap();
+    /// ```
+    pub fn set_jitter(&self, jitter: ToxicValueType) -> Result<ToxicPack, ToxiproxyError> {
+        self.set_attribute("jitter", jitter)
+    }
+
+    /// Sets the toxicity of the toxic on the server and returns the refreshed toxic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxics = proxy.with_latency("downstream".into(), 2000, 0, 1.0).unwrap().toxics().unwrap();
+    /// let toxic = toxics[0].set_toxicity(0.5).unwrap();
+    /// ```
+    pub fn set_toxicity(&self, toxicity: f32) -> Result<ToxicPack, ToxiproxyError> {
+        self.post_update(serde_json::json!({ "toxicity": toxicity }))
+    }
+
+    fn set_attribute(&self, key: &str, value: ToxicValueType) -> Result<ToxicPack, ToxiproxyError> {
+        self.post_update(serde_json::json!({ "attributes": { key: value } }))
+    }
+
+    /// Sets the toxicity and attributes of the toxic on the server in place - e.g. to ramp up a
+    /// latency mid-test without recreating the toxic - and updates `self` to the result.
+    /// Attributes left out keep their value: `attributes` is merged into the current attributes
+    /// on the server, fetched first, and the full set is sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let mut toxics = proxy.with_latency("downstream".into(), 100, 0, 1.0).unwrap().toxics().unwrap();
+    /// let mut attributes = HashMap::new();
+    /// attributes.insert("latency".into(), 2000);
+    /// toxics[0].update(1.0, attributes).unwrap();
+    /// ```
+    pub fn update(
+        &mut self,
+        toxicity: f32,
+        attributes: HashMap<String, ToxicValueType>,
+    ) -> Result<(), ToxiproxyError> {
+        let (proxy_name, client) = self.binding()?;
+        let path = format!("proxies/{}/toxics/{}", proxy_name, self.name);
+
+        let mut merged = lock_client(client)
+            .get(&path)
+            .and_then(read_json::<ToxicPack>)?
+            .attributes;
+        merged.extend(
+            attributes
+                .into_iter()
+                .map(|(key, value)| (key, Value::from(value))),
+        );
+
+        *self = self.post_update(serde_json::json!({
+            "toxicity": toxicity,
+            "attributes": merged,
+        }))?;
+        Ok(())
+    }
+
+    /// Removes the toxic from its proxy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// let toxics = proxy.with_latency("downstream".into(), 2000, 0, 1.0).unwrap().toxics().unwrap();
+    /// toxics[0].delete().unwrap();
+    /// ```
+    pub fn delete(&self) -> Result<(), ToxiproxyError> {
+        let (proxy_name, client) = self.binding()?;
+        let path = format!("proxies/{}/toxics/{}", proxy_name, self.name);
+
+        lock_client(client).delete(&path).and_then(check_status)?;
+        self.trace("removed", proxy_name);
+        Ok(())
+    }
+
+    fn post_update(&self, payload: Value) -> Result<ToxicPack, ToxiproxyError> {
+        let (proxy_name, client) = self.binding()?;
+
+        let body = serde_json::to_string(&payload)?;
+        let path = format!("proxies/{}/toxics/{}", proxy_name, self.name);
+
+        lock_client(client)
+            .post_with_data(&path, body)
+            .and_then(read_json::<ToxicPack>)
+            .map(|toxic| {
+                toxic.trace("updated", proxy_name);
+                toxic.with_client(proxy_name, client.clone())
+            })
+    }
+
+    /// Proxy and client the toxic was fetched through, see [`ERR_TOXIC_UNBOUND`].
+    fn binding(&self) -> Result<(&str, &Arc<Mutex<HttpClient>>), ToxiproxyError> {
+        match (&self.proxy_name, &self.client) {
+            (Some(proxy_name), Some(client)) => Ok((proxy_name, client)),
+            _ => Err(ERR_TOXIC_UNBOUND.into()),
+        }
+    }
+
+    /// Emits an audit event (behind the `tracing` feature) about a lifecycle change of the toxic.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace(&self, action: &'static str, proxy: &str) {
+        tracing::info!(
+            target: "toxiproxy_rust::audit",
+            action,
+            proxy,
+            name = self.name.as_str(),
+            toxic_type = self.r#type.as_str(),
+            stream = self.stream.as_deref().unwrap_or(""),
+            toxicity = %self.toxicity,
+            attributes = %serde_json::to_string(&self.attributes).unwrap_or_default(),
+            "toxic {}",
+            action
+        );
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn trace(&self, _action: &'static str, _proxy: &str) {}
+}
+
+/// Builds a Toxic of any [type]. Attributes of the known toxic types are validated: required ones
+/// must be present and unknown ones (e.g. typos) are rejected. Types unknown to the crate, such as
+/// plugin toxics, are not validated.
+///
+/// # Examples
+///
+/// ```
+/// # use toxiproxy_rust::toxic::ToxicBuilder;
+/// let toxic = ToxicBuilder::new("latency".into())
+///     .stream("upstream".into())
+///     .toxicity(0.5)
+///     .attribute("latency".into(), 2000)
+///     .build()
+///     .expect("toxic is valid");
+/// ```
+///
+/// [type]: https://github.com/Shopify/toxiproxy#toxics
+#[derive(Debug, Clone)]
+pub struct ToxicBuilder {
+    r#type: String,
+    name: Option<String>,
+    stream: Option<String>,
+    toxicity: f32,
+    attributes: ToxicAttributes,
+}
+
+impl ToxicBuilder {
+    /// Starts a downstream Toxic of the given type with a toxicity of 1.0.
+    pub fn new(r#type: String) -> Self {
+        Self {
+            r#type,
+            name: None,
+            stream: Some("downstream".into()),
+            toxicity: 1.0,
+            attributes: HashMap::new(),
         }
     }
+
+    /// Parses a compact spec like `downstream latency=500 jitter=50 @0.8`, e.g. from a command
+    /// line argument or an environment variable:
+    ///
+    /// - an optional stream, `upstream` or `downstream` (the default),
+    /// - the type, either on its own (`reset_peer`) or as the attribute named like it
+    ///   (`latency=500`),
+    /// - further `key=value` attributes with numeric values,
+    /// - an optional `@toxicity`, 1.0 by default.
+    ///
+    /// The attributes are validated by [`build`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// let toxic = ToxicBuilder::from_spec("upstream bandwidth rate=100 @0.5")
+    ///     .and_then(ToxicBuilder::build)
+    ///     .expect("spec is valid");
+    /// assert_eq!("bandwidth_upstream", toxic.name);
+    /// ```
+    ///
+    /// [`build`]: #method.build
+    pub fn from_spec(spec: &str) -> Result<Self, ToxiproxyError> {
+        let invalid = |reason: String| -> ToxiproxyError {
+            format!("invalid toxic spec `{}`: {}", spec, reason).into()
+        };
+        let mut tokens = spec.split_whitespace().peekable();
+
+        let stream = match tokens.peek() {
+            Some(&stream) if stream == "upstream" || stream == "downstream" => {
+                tokens.next();
+                stream
+            }
+            _ => "downstream",
+        };
+        let mut builder = match tokens.next() {
+            Some(token) if !token.starts_with('@') => match token.split_once('=') {
+                Some((r#type, value)) => Self::new(r#type.into())
+                    .attribute(r#type.into(), parse_spec_value(value).map_err(invalid)?),
+                None => Self::new(token.into()),
+            },
+            _ => return Err(invalid("the toxic type is missing".into())),
+        }
+        .stream(stream.into());
+
+        let mut toxicity = None;
+        for token in tokens {
+            if let Some(value) = token.strip_prefix('@') {
+                let value = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|value| (0.0..=1.0).contains(value))
+                    .ok_or_else(|| invalid(format!("toxicity `{}` is not in [0, 1]", value)))?;
+                if toxicity.replace(value).is_some() {
+                    return Err(invalid("the toxicity is given twice".into()));
+                }
+                continue;
+            }
+
+            let (key, value) = token
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("`{}` is not a key=value attribute", token)))?;
+            if builder.attributes.contains_key(key) {
+                return Err(invalid(format!("`{}` is given twice", key)));
+            }
+            builder = builder.attribute(key.into(), parse_spec_value(value).map_err(invalid)?);
+        }
+
+        Ok(builder.toxicity(toxicity.unwrap_or(1.0)))
+    }
+
+    /// Overrides the default `{type}_{stream}` name.
+    pub fn name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Sets the stream, `upstream` or `downstream`.
+    pub fn stream(mut self, stream: String) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Leaves out the stream, for custom toxics which don't apply to one. The default name is
+    /// then just the type.
+    pub fn without_stream(mut self) -> Self {
+        self.stream = None;
+        self
+    }
+
+    /// Sets the probability of the Toxic being applied to a connection.
+    pub fn toxicity(mut self, toxicity: f32) -> Self {
+        self.toxicity = toxicity;
+        self
+    }
+
+    /// Sets an attribute. Any JSON value is accepted, for the attributes of plugin toxics.
+    pub fn attribute<V: Into<Value>>(mut self, key: String, value: V) -> Self {
+        self.attributes.insert(key, value.into());
+        self
+    }
+
+    /// Sets multiple attributes.
+    pub fn attributes<V: Into<Value>>(mut self, attributes: HashMap<String, V>) -> Self {
+        self.attributes.extend(
+            attributes
+                .into_iter()
+                .map(|(key, value)| (key, value.into())),
+        );
+        self
+    }
+
+    /// Validates the attributes and builds the Toxic. Only the attribute names and integer types
+    /// are checked for the known toxic types, the ranges are left to the server.
+    pub fn build(self) -> Result<ToxicPack, ToxiproxyError> {
+        if let Some((required, optional)) = attribute_schema(&self.r#type) {
+            for (key, value) in &self.attributes {
+                if !required.contains(&key.as_str()) && !optional.contains(&key.as_str()) {
+                    return Err(format!(
+                        "{} toxic has no `{}` attribute, expected one of: {}",
+                        self.r#type,
+                        key,
+                        required
+                            .iter()
+                            .chain(optional.iter())
+                            .cloned()
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    )
+                    .into());
+                }
+                if !value.is_i64() && !value.is_u64() {
+                    return Err(format!(
+                        "{} toxic expects an integer for `{}`, got {}",
+                        self.r#type, key, value
+                    )
+                    .into());
+                }
+            }
+
+            for key in required {
+                if !self.attributes.contains_key(*key) {
+                    return Err(
+                        format!("{} toxic requires the `{}` attribute", self.r#type, key).into(),
+                    );
+                }
+            }
+        }
+
+        let mut toxic =
+            ToxicPack::with_stream(self.r#type, self.stream, self.toxicity, self.attributes);
+        if let Some(name) = self.name {
+            toxic.name = name;
+        }
+        Ok(toxic)
+    }
+}
+
+/// Numeric attribute value of a toxic spec.
+fn parse_spec_value(value: &str) -> Result<Value, String> {
+    match serde_json::from_str::<Value>(value) {
+        Ok(number @ Value::Number(_)) => Ok(number),
+        _ => Err(format!("`{}` is not a number", value)),
+    }
+}
+
+/// Required and optional attributes of the known toxic types.
+fn attribute_schema(r#type: &str) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    match r#type {
+        "latency" => Some((&["latency"], &["jitter"])),
+        "bandwidth" => Some((&["rate"], &[])),
+        "slow_close" => Some((&["delay"], &[])),
+        "timeout" => Some((&["timeout"], &[])),
+        "slicer" => Some((&["average_size"], &["size_variation", "delay"])),
+        "limit_data" => Some((&["bytes"], &[])),
+        "reset_peer" => Some((&[], &["timeout"])),
+        _ => None,
+    }
+}
+
+/// Differences between expected toxics and the ones active on the server, see
+/// [`Proxy::diff_toxics`]. Toxics are matched by name.
+///
+/// [`Proxy::diff_toxics`]: ../proxy/struct.Proxy.html#method.diff_toxics
+#[derive(Debug, Clone, Default)]
+pub struct ToxicDiff {
+    /// Expected toxics that are not active.
+    pub missing: Vec<ToxicPack>,
+    /// Active toxics that were not expected.
+    pub extra: Vec<ToxicPack>,
+    /// Toxics active under the expected name, but with a different configuration.
+    pub mismatched: Vec<ToxicMismatch>,
+}
+
+impl ToxicDiff {
+    /// True if the active toxics are exactly the expected ones.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+
+    pub(crate) fn new(expected: &[ToxicPack], active: Vec<ToxicPack>) -> Self {
+        let mut diff = Self::default();
+
+        for toxic in expected {
+            match active.iter().find(|active| active.name == toxic.name) {
+                Some(active) if !toxic.matches(active) => diff.mismatched.push(ToxicMismatch {
+                    expected: toxic.clone(),
+                    active: active.clone(),
+                }),
+                Some(_) => {}
+                None => diff.missing.push(toxic.clone()),
+            }
+        }
+        diff.extra = active
+            .into_iter()
+            .filter(|active| !expected.iter().any(|toxic| toxic.name == active.name))
+            .collect();
+
+        diff
+    }
+}
+
+/// Expected and active configuration of a toxic, see [`ToxicDiff`].
+#[derive(Debug, Clone)]
+pub struct ToxicMismatch {
+    pub expected: ToxicPack,
+    pub active: ToxicPack,
+}
+
+/// Handle of a toxic that is removed in the background once its duration elapsed.
+/// Dropping the handle doesn't stop the removal, use [`cancel`] to keep the toxic.
+///
+/// [`cancel`]: #method.cancel
+#[derive(Debug)]
+pub struct TransientToxic {
+    cancel: Sender<()>,
+    remover: JoinHandle<Result<(), ToxiproxyError>>,
+}
+
+impl TransientToxic {
+    pub(crate) fn new(
+        proxy_name: String,
+        toxic_name: String,
+        client: Arc<Mutex<HttpClient>>,
+        duration: Duration,
+    ) -> Self {
+        let (cancel, cancelled) = channel();
+        let deadline = Instant::now() + duration;
+
+        let remover = spawn(move || {
+            match cancelled.recv_timeout(duration) {
+                Ok(()) => return Ok(()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    std::thread::sleep(deadline.saturating_duration_since(Instant::now()))
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+            }
+
+            let path = format!("proxies/{}/toxics/{}", proxy_name, toxic_name);
+            lock_client(&client)
+                .delete(&path)
+                .and_then(check_status)
+                .map(|_| ())
+        });
+
+        Self { cancel, remover }
+    }
+
+    /// Keeps the toxic active - it won't be removed after the duration.
+    pub fn cancel(self) {
+        let _ = self.cancel.send(());
+        let _ = self.remover.join();
+    }
+
+    /// Blocks until the toxic was removed.
+    pub fn join(self) -> Result<(), ToxiproxyError> {
+        let Self { cancel, remover } = self;
+        // Keep the channel open so the remover waits for the full duration.
+        let result = remover
+            .join()
+            .map_err(|_| "toxic remover thread panicked".to_owned())?;
+        drop(cancel);
+        result
+    }
+}
+
+/// Handle of a toxic that is removed when the handle is dropped, see
+/// [`Proxy::add_toxic_guarded`]. Scopes a toxic to a block, and several guards compose - unlike
+/// [`Proxy::apply`], only the guarded toxic is removed. Failures to remove it are logged.
+///
+/// [`Proxy::add_toxic_guarded`]: ../proxy/struct.Proxy.html#method.add_toxic_guarded
+/// [`Proxy::apply`]: ../proxy/struct.Proxy.html#method.apply
+#[derive(Debug)]
+pub struct ToxicGuard {
+    proxy_name: String,
+    name: String,
+    client: Arc<Mutex<HttpClient>>,
+}
+
+impl ToxicGuard {
+    /// Name of the guarded toxic.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Guards a toxic as returned by the server, e.g. by [`Proxy::replace_toxic`]. Fails for a toxic
+/// not bound to a proxy, like one just built with [`ToxicBuilder`].
+///
+/// [`Proxy::replace_toxic`]: ../proxy/struct.Proxy.html#method.replace_toxic
+impl TryFrom<ToxicPack> for ToxicGuard {
+    type Error = ToxiproxyError;
+
+    fn try_from(toxic: ToxicPack) -> Result<Self, ToxiproxyError> {
+        let (proxy_name, client) = toxic.binding()?;
+
+        Ok(Self {
+            proxy_name: proxy_name.to_owned(),
+            client: client.clone(),
+            name: toxic.name,
+        })
+    }
+}
+
+impl Drop for ToxicGuard {
+    fn drop(&mut self) {
+        let path = format!("proxies/{}/toxics/{}", self.proxy_name, self.name);
+
+        if let Err(err) = lock_client(&self.client)
+            .delete(&path)
+            .and_then(check_status)
+        {
+            log::warn!(
+                "toxic `{}` of proxy `{}` cannot be removed: {}",
+                self.name,
+                self.proxy_name,
+                err
+            );
+        }
+    }
+}
+
+/// Handle of a background watch on the toxics of a proxy, see [`Proxy::watch_toxics`]. Dropping
+/// the handle stops the watch as well.
+///
+/// [`Proxy::watch_toxics`]: ../proxy/struct.Proxy.html#method.watch_toxics
+#[derive(Debug)]
+pub struct ToxicWatch {
+    stop: Sender<()>,
+    watcher: JoinHandle<()>,
+}
+
+impl ToxicWatch {
+    pub(crate) fn new<R, C>(
+        mut current: Vec<ToxicPack>,
+        interval: Duration,
+        refresh: R,
+        mut callback: C,
+    ) -> Self
+    where
+        R: Fn() -> Result<Vec<ToxicPack>, ToxiproxyError> + Send + 'static,
+        C: FnMut(&[ToxicPack]) + Send + 'static,
+    {
+        let (stop, stopped) = channel::<()>();
+
+        let watcher = spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                match refresh() {
+                    Ok(toxics) if !same_toxics(&current, &toxics) => {
+                        callback(&toxics);
+                        current = toxics;
+                    }
+                    Ok(_) => (),
+                    Err(err) => log::warn!("toxics cannot be refreshed: {}", err),
+                }
+            }
+        });
+
+        Self { stop, watcher }
+    }
+
+    /// Stops watching, waiting for a running callback to finish.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.watcher.join();
+    }
+}
+
+/// Compares the configuration of two sets of toxics, regardless of their order.
+fn same_toxics(left: &[ToxicPack], right: &[ToxicPack]) -> bool {
+    left.len() == right.len()
+        && left.iter().all(|toxic| {
+            right.iter().any(|other| {
+                toxic.name == other.name && toxic.matches(other) && other.matches(toxic)
+            })
+        })
 }
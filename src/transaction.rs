@@ -0,0 +1,107 @@
+//! All-or-nothing setup of proxies and toxics. Resources created through a [`Transaction`] are
+//! deleted again when the setup fails half-way.
+
+use super::error::*;
+use super::http_client::*;
+use super::proxy::*;
+use super::toxic::ToxicPack;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+enum Resource {
+    Proxy(String),
+    Toxic { proxy: String, name: String },
+}
+
+/// Records the proxies and toxics created during [`Client::transaction`].
+///
+/// Only resources created through the transaction itself are rolled back - toxics registered
+/// directly on a returned [`Proxy`] (e.g. with `with_latency`) are not tracked.
+///
+/// [`Client::transaction`]: ../client/struct.Client.html#method.transaction
+pub struct Transaction {
+    client: Arc<Mutex<HttpClient>>,
+    created: RefCell<Vec<Resource>>,
+}
+
+impl Transaction {
+    pub(crate) fn new(client: Arc<Mutex<HttpClient>>) -> Self {
+        Self {
+            client,
+            created: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Creates a new proxy. Fails if a proxy with the same name already exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::proxy::ProxyPack;
+    /// # let _ = toxiproxy_rust::TOXIPROXY.find_proxy("transaction_doc").map(|proxy| proxy.delete());
+    /// toxiproxy_rust::TOXIPROXY.transaction(|transaction| {
+    ///     let proxy = transaction.proxy(ProxyPack::new(
+    ///         "transaction_doc".into(),
+    ///         "localhost:2010".into(),
+    ///         "localhost:2000".into(),
+    ///     ))?;
+    ///     # proxy.delete()?;
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn proxy(&self, proxy_pack: ProxyPack) -> Result<Proxy, ToxiproxyError> {
+        let client = lock_client(&self.client);
+        let proxy_pack = proxy_pack.create(&client)?;
+
+        self.created
+            .borrow_mut()
+            .push(Resource::Proxy(proxy_pack.name.clone()));
+
+        Ok(Proxy::new(proxy_pack, self.client.clone()))
+    }
+
+    /// Registers a toxic on `proxy`, see [`ToxicBuilder`] for building one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use toxiproxy_rust::toxic::ToxicBuilder;
+    /// # toxiproxy_rust::TOXIPROXY.populate(vec![toxiproxy_rust::proxy::ProxyPack::new(
+    /// #    "socket".into(),
+    /// #    "localhost:2001".into(),
+    /// #    "localhost:2000".into(),
+    /// # )]);
+    /// let proxy = toxiproxy_rust::TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    /// toxiproxy_rust::TOXIPROXY.transaction(|transaction| {
+    ///     let toxic = ToxicBuilder::new("latency".into()).attribute("latency".into(), 2000).build()?;
+    ///     transaction.toxic(&proxy, toxic)
+    /// });
+    /// # proxy.delete_all_toxics();
+    /// ```
+    ///
+    /// [`ToxicBuilder`]: ../toxic/struct.ToxicBuilder.html
+    pub fn toxic(&self, proxy: &Proxy, toxic: ToxicPack) -> Result<ToxicPack, ToxiproxyError> {
+        let toxic = proxy.add_toxic(toxic)?;
+
+        self.created.borrow_mut().push(Resource::Toxic {
+            proxy: proxy.proxy_pack.name.clone(),
+            name: toxic.name.clone(),
+        });
+
+        Ok(toxic)
+    }
+
+    /// Deletes everything created so far, newest first. Failures are ignored so that as much as
+    /// possible gets cleaned up.
+    pub(crate) fn rollback(self) {
+        let client = lock_client(&self.client);
+
+        for resource in self.created.into_inner().into_iter().rev() {
+            let path = match resource {
+                Resource::Proxy(name) => format!("proxies/{}", name),
+                Resource::Toxic { proxy, name } => format!("proxies/{}/toxics/{}", proxy, name),
+            };
+            let _ = client.delete(&path);
+        }
+    }
+}
@@ -18,162 +18,2990 @@ fn test_is_running() {
     assert!(TOXIPROXY.is_running());
 }
 
+#[test]
+fn test_global_client_initializes_once() {
+    let first: &client::Client = &TOXIPROXY;
+    let second: &client::Client = &TOXIPROXY;
+
+    assert!(std::ptr::eq(first, second));
+    assert!(first.is_running());
+}
+
+#[test]
+fn test_wait_until_running() {
+    assert!(TOXIPROXY.wait_until_running(Duration::from_secs(1)).is_ok());
+
+    let unused = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let start = SystemTime::now();
+    let result = client::Client::new(unused).wait_until_running(Duration::from_millis(200));
+
+    assert!(result.is_err());
+    assert!(start.elapsed().unwrap() >= Duration::from_millis(200));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_wait_until_running() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let client = async_client::AsyncClient::new("127.0.0.1:8474").unwrap();
+        assert!(client.is_running().await);
+        assert!(client
+            .wait_until_running(Duration::from_secs(1))
+            .await
+            .is_ok());
+    });
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_wait_until_running_cancelled() {
+    // Accepts the request but never answers it, until the client closes the connection.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (closed_tx, closed_rx) = std::sync::mpsc::channel();
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buf = [0u8; 1024];
+        loop {
+            match stream.read(&mut buf) {
+                Ok(0) => break closed_tx.send(true).unwrap(),
+                Ok(_) => continue,
+                Err(_) => break closed_tx.send(false).unwrap(),
+            }
+        }
+    });
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let (raced, closed) = runtime.block_on(async {
+        let client = async_client::AsyncClient::new(addr).unwrap();
+        let raced = tokio::time::timeout(
+            Duration::from_millis(200),
+            client.wait_until_running(Duration::from_secs(5)),
+        )
+        .await;
+
+        let mut closed = None;
+        for _ in 0..100 {
+            if let Ok(value) = closed_rx.try_recv() {
+                closed = Some(value);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (raced, closed)
+    });
+
+    assert!(raced.is_err());
+    assert_eq!(Some(true), closed);
+}
+
+#[cfg(feature = "docker")]
+#[test]
+#[ignore = "requires Docker"]
+fn test_container() {
+    let toxiproxy = container::ToxiproxyContainer::start(&[2021]).unwrap();
+
+    let proxies = toxiproxy.populate(vec![ProxyPack::new(
+        "container".into(),
+        "0.0.0.0:2021".into(),
+        "host.docker.internal:2000".into(),
+    )]);
+    assert!(proxies.is_ok());
+    assert!(toxiproxy.find_proxy("container").is_ok());
+
+    let id = toxiproxy.id().to_owned();
+    drop(toxiproxy);
+    let inspected = std::process::Command::new("docker")
+        .args(["inspect", &id])
+        .output()
+        .unwrap();
+    assert!(!inspected.status.success());
+}
+
+#[test]
+fn test_is_running_with_hostname() {
+    let client = client::Client::new("localhost:8474");
+    assert!(client.is_running());
+}
+
+#[test]
+fn test_with_client() {
+    // Accepts connections but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let http_client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    let client = client::Client::with_client(listener.local_addr().unwrap(), http_client);
+
+    let start = SystemTime::now();
+    let result = client.reset();
+
+    assert!(result.is_err());
+    assert!(start.elapsed().unwrap() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_sharing_pool() {
+    let (first_server, second_server) =
+        (mock_server(vec![(204, "")]), mock_server(vec![(204, "")]));
+    // Accepts connections but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let http_client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+    let first = client::Client::with_client(first_server.addr.as_str(), http_client);
+    let second = first.sharing_pool(second_server.addr.as_str()).unwrap();
+    let silent = first.sharing_pool(listener.local_addr().unwrap()).unwrap();
+
+    assert!(first.reset().is_ok());
+    assert!(second.reset().is_ok());
+    assert_eq!(1, first_server.requests.lock().unwrap().len());
+    assert_eq!(1, second_server.requests.lock().unwrap().len());
+
+    // The timeout of the shared client applies.
+    let start = SystemTime::now();
+    assert!(silent.reset().is_err());
+    assert!(start.elapsed().unwrap() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_sharing_pool_unresolved() {
+    let server = mock_server(vec![(204, "")]);
+    let first = client::Client::new(server.addr.as_str());
+
+    let unresolved = first.sharing_pool("toxiproxy.invalid:8474");
+    let empty = first.sharing_pool(&[][..] as &[std::net::SocketAddr]);
+
+    assert!(unresolved
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("Incorrect address: "));
+    assert!(empty
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("Incorrect address: "));
+    assert!(first.reset().is_ok());
+}
+
+#[test]
+fn test_constructors_unresolved() {
+    let with_timeouts = client::Client::with_timeouts(
+        "toxiproxy.invalid:8474",
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+    );
+    let new = std::panic::catch_unwind(|| client::Client::new("toxiproxy.invalid:8474"));
+
+    assert!(with_timeouts
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("Incorrect address: "));
+    assert!(new.is_err());
+}
+
+#[test]
+fn test_with_timeouts() {
+    // Accepts connections but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let silent = client::Client::with_timeouts(
+        listener.local_addr().unwrap(),
+        Duration::from_millis(200),
+        Duration::from_millis(200),
+    )
+    .unwrap();
+    let unroutable = client::Client::with_timeouts(
+        "10.255.255.1:8474",
+        Duration::from_secs(5),
+        Duration::from_millis(200),
+    )
+    .unwrap();
+
+    let start = SystemTime::now();
+    let result = silent.all();
+    assert!(start.elapsed().unwrap() < Duration::from_secs(2));
+    assert!(matches!(result, Err(error::ToxiproxyError::Http(err)) if err.is_timeout()));
+
+    let start = SystemTime::now();
+    assert!(unroutable.all().is_err());
+    assert!(start.elapsed().unwrap() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_poisoned_lock() {
+    let server = mock_server(vec![(204, "")]);
+    let client = client::Client::new(server.addr.as_str());
+    let panicked = std::sync::atomic::AtomicBool::new(false);
+    client.on_reset(move |event| {
+        if event == client::ResetEvent::BeforeReset
+            && !panicked.swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            panic!("reset hook has failed");
+        }
+    });
+
+    // The hook panics while the reset holds the lock of all requests.
+    let panicking = client.clone();
+    let result = spawn(move || {
+        let _ = panicking.reset();
+    })
+    .join();
+    assert!(result.is_err());
+
+    assert!(client.reset().is_ok());
+    assert_eq!(1, server.requests.lock().unwrap().len());
+}
+
+#[test]
+fn test_proxy_ensure_latency_error() {
+    let server = mock_server(vec![
+        (
+            200,
+            r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":1000,"jitter":0}}"#,
+        ),
+        (500, r#"{"error":"internal error","status":500}"#),
+    ]);
+
+    let result = client::Client::new(server.addr.as_str())
+        .proxy_handle("socket")
+        .ensure_latency("downstream".into(), 2000, 0, 1.0)
+        .map(|_| ());
+
+    assert_eq!(
+        "server error 500: internal error",
+        result.err().unwrap().to_string()
+    );
+    let requests = server.requests.lock().unwrap();
+    assert!(requests[0].starts_with("GET /proxies/socket/toxics/latency_downstream "));
+    assert!(requests[1].starts_with("POST /proxies/socket/toxics/latency_downstream "));
+}
+
 #[test]
 fn test_reset() {
     assert!(TOXIPROXY.reset().is_ok());
 }
 
 #[test]
-fn test_populate() {
-    let result = TOXIPROXY.populate(vec![ProxyPack::new(
-        "socket".into(),
-        "localhost:2001".into(),
-        "localhost:2000".into(),
-    )]);
+fn test_reset_is_exclusive() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let setup = spawn(move || {
+        for _ in 0..50 {
+            let _ = proxy.with_latency("downstream".into(), 1000, 0, 1.0);
+            let _ = proxy.remove_latency("downstream");
+        }
+    });
+    // A toxic added between the reset and its check would fail the verification.
+    let resets = (0..20)
+        .map(|_| TOXIPROXY.reset_verified())
+        .collect::<Vec<_>>();
+    setup.join().expect("Failed joining setup thread");
+
+    assert!(TOXIPROXY.reset().is_ok());
+    assert!(resets.iter().all(Result::is_ok));
+}
+
+#[test]
+fn test_reset_verified() {
+    assert!(TOXIPROXY.reset_verified().is_ok());
+
+    let server = mock_server(vec![
+        (204, ""),
+        (
+            200,
+            r#"{"socket":{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,
+                "toxics":[{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":1000}}]}}"#,
+        ),
+    ]);
+    let result = client::Client::new(server.addr.as_str()).reset_verified();
+
+    assert_eq!(
+        "reset left toxics on proxies: socket",
+        result.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_on_reset() {
+    let server = mock_server(vec![(204, "")]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let events = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let (recorded, requests) = (events.clone(), server.requests.clone());
+    client.on_reset(move |event| {
+        let sent = requests.lock().unwrap().len();
+        recorded.lock().unwrap().push((event, sent));
+    });
+    assert!(client.reset().is_ok());
+
+    assert_eq!(
+        vec![
+            (client::ResetEvent::BeforeReset, 0),
+            (client::ResetEvent::AfterReset, 1)
+        ],
+        *events.lock().unwrap()
+    );
+}
+
+#[test]
+fn test_proxy_listen_addr() {
+    let server = mock_server(vec![
+        (
+            200,
+            r#"{"name":"a","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]}"#,
+        ),
+        (
+            200,
+            r#"{"name":"b","listen":"[::1]:2001","upstream":"localhost:2000","enabled":true,"toxics":[]}"#,
+        ),
+        (
+            200,
+            r#"{"name":"c","listen":":2001","upstream":"localhost:2000","enabled":true,"toxics":[]}"#,
+        ),
+    ]);
+    let client = client::Client::new(server.addr.as_str());
+
+    for (name, expected) in &[
+        ("a", "127.0.0.1:2001"),
+        ("b", "[::1]:2001"),
+        ("c", "127.0.0.1:2001"),
+    ] {
+        let proxy = client.find_proxy(name).unwrap();
+        assert_eq!(
+            expected.parse::<std::net::SocketAddr>().unwrap(),
+            proxy.listen_addr().unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_error_status() {
+    let server = mock_server(vec![
+        (
+            409,
+            r#"{"error":"listen tcp 127.0.0.1:2001: bind: address already in use","status":409}"#,
+        ),
+        (404, r#"{"error":"proxy not found","status":404}"#),
+    ]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let in_use = client
+        .create_proxy("in_use", "127.0.0.1:2001", "localhost:2000")
+        .err()
+        .unwrap();
+    let missing = client.proxy_handle("missing").delete().err().unwrap();
+
+    assert!(matches!(
+        &in_use,
+        error::ToxiproxyError::Server { status: 409, message }
+            if message == "listen tcp 127.0.0.1:2001: bind: address already in use"
+    ));
+    assert_eq!(
+        "server error 409: listen tcp 127.0.0.1:2001: bind: address already in use",
+        in_use.to_string()
+    );
+    assert!(matches!(
+        &missing,
+        error::ToxiproxyError::Server { status: 404, message } if message == "proxy not found"
+    ));
+}
+
+#[test]
+fn test_error_http() {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let result = client::Client::new(addr).all();
+
+    let err = result.err().unwrap();
+    assert!(matches!(err, error::ToxiproxyError::Http(_)));
+    assert!(err.to_string().starts_with("http error: "));
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn test_error_serde() {
+    let server = mock_server(vec![(200, "not json")]);
+
+    let result = client::Client::new(server.addr.as_str()).all();
+
+    assert!(matches!(
+        result.err().unwrap(),
+        error::ToxiproxyError::Serde(_)
+    ));
+}
+
+#[test]
+fn test_reset_except() {
+    let result = TOXIPROXY.populate(vec![
+        ProxyPack::new(
+            "socket".into(),
+            "localhost:2001".into(),
+            "localhost:2000".into(),
+        ),
+        ProxyPack::new(
+            "socket_kept".into(),
+            "localhost:2002".into(),
+            "localhost:2000".into(),
+        ),
+    ]);
+    assert!(result.is_ok());
+
+    for proxy in result.as_ref().unwrap() {
+        proxy
+            .with_latency("downstream".into(), 2000, 0, 1.0)
+            .unwrap();
+    }
+
+    assert!(TOXIPROXY.reset_except(&["socket_kept"]).is_ok());
+
+    let socket = TOXIPROXY.find_proxy("socket").unwrap();
+    assert_eq!(0, socket.toxics().unwrap().len());
+
+    let socket_kept = TOXIPROXY.find_proxy("socket_kept").unwrap();
+    assert_eq!(1, socket_kept.toxics().unwrap().len());
+
+    assert!(socket_kept.delete().is_ok());
+}
+
+#[test]
+fn test_reset_toxics_only() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+    assert!(proxy.disable().is_ok());
+
+    assert!(TOXIPROXY.reset_toxics_only().is_ok());
+
+    let proxy = TOXIPROXY.find_proxy("socket").unwrap();
+    assert!(proxy.enable().is_ok());
+    assert!(!proxy.proxy_pack.enabled);
+    assert!(proxy.proxy_pack.toxics.is_empty());
+}
+
+#[test]
+fn test_reset_toxics_only_never_enables() {
+    let server = mock_server(vec![
+        (
+            200,
+            r#"{"down":{"name":"down","listen":"127.0.0.1:2002","upstream":"localhost:2000","enabled":false,"toxics":[]}}"#,
+        ),
+        (
+            200,
+            r#"[{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":1000}}]"#,
+        ),
+        (204, ""),
+    ]);
+
+    let result = client::Client::new(server.addr.as_str()).reset_toxics_only();
+
+    assert!(result.is_ok());
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(3, requests.len());
+    assert!(requests[2].starts_with("DELETE /proxies/down/toxics/latency_downstream "));
+    assert!(!requests.iter().any(|request| request.starts_with("POST ")));
+}
+
+#[test]
+fn test_find_proxy_keeps_toxics() {
+    populate_example();
+    TOXIPROXY
+        .find_and_reset_proxy("socket")
+        .unwrap()
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+
+    let proxy = TOXIPROXY.find_proxy("socket").unwrap();
+    let toxics = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert_eq!(1, proxy.proxy_pack.toxics.len());
+    assert_eq!(1, toxics.unwrap().len());
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn test_from_url() {
+    let server = mock_server(vec![(204, "")]);
+    let url = url::Url::parse(&format!("http://{}/toxiproxy", server.addr)).unwrap();
+
+    let client = client::Client::from_url(url).unwrap();
+    assert!(client.reset().is_ok());
+    assert!(server.requests.lock().unwrap()[0].starts_with("POST /toxiproxy/reset "));
+
+    let url = url::Url::parse("https://127.0.0.1:8474").unwrap();
+    assert!(client::Client::from_url(url).is_err());
+}
+
+#[test]
+fn test_from_uri() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = client::Client::from_uri(format!("http://{}", addr)).unwrap();
+    assert!(client.is_running());
+    assert!(client::Client::from_uri(addr.to_string())
+        .unwrap()
+        .is_running());
+
+    drop(listener);
+    assert!(!client.is_running());
+    assert!(client::Client::from_uri("https://127.0.0.1:8474").is_err());
+    assert!(client::Client::from_uri("http://").is_err());
+}
+
+#[test]
+fn test_from_env() {
+    // Initialized before the variable is set, so that other tests keep the default server.
+    assert!(TOXIPROXY.is_running());
+    let server = mock_server(vec![(204, "")]);
+    let configured = std::env::var(TOXIPROXY_URL_ENV);
+
+    std::env::set_var(
+        TOXIPROXY_URL_ENV,
+        format!("http://{}/toxiproxy", server.addr),
+    );
+    let client = client::Client::from_env();
+    std::env::set_var(TOXIPROXY_URL_ENV, "ftp://127.0.0.1:8474");
+    let invalid = client::Client::from_env();
+    match configured {
+        Ok(uri) => std::env::set_var(TOXIPROXY_URL_ENV, uri),
+        Err(_) => std::env::remove_var(TOXIPROXY_URL_ENV),
+    }
+
+    assert!(client.unwrap().reset().is_ok());
+    assert!(server.requests.lock().unwrap()[0].starts_with("POST /toxiproxy/reset "));
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn test_base_path() {
+    let server = mock_server(vec![(204, ""), (200, "{}")]);
+
+    let client = client::Client::new(server.addr.as_str()).with_base_path("/toxiproxy/");
+    assert!(client.reset().is_ok());
+    assert!(client.all().is_ok());
+
+    let requests = server.requests.lock().unwrap();
+    assert!(requests[0].starts_with("POST /toxiproxy/reset "));
+    assert!(requests[1].starts_with("GET /toxiproxy/proxies "));
+}
+
+#[test]
+fn test_max_response_size() {
+    let server = mock_server(vec![(200, r#"{"socket":{}}"#), (200, "{}")]);
+    let client = client::Client::new(server.addr.as_str()).with_max_response_size(4);
+
+    let result = client.all();
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("Response too large"));
+
+    let client = client.with_max_response_size(2);
+    assert!(client.all().unwrap().is_empty());
+}
+
+#[test]
+fn test_without_content_type() {
+    let server = mock_server(vec![(204, ""), (204, "")]);
+
+    assert!(client::Client::new(server.addr.as_str()).reset().is_ok());
+    assert!(client::Client::new(server.addr.as_str())
+        .without_content_type()
+        .reset()
+        .is_ok());
+
+    let headers = server.headers.lock().unwrap();
+    assert!(headers[0]
+        .to_lowercase()
+        .contains("content-type: application/json"));
+    assert!(!headers[1].to_lowercase().contains("content-type"));
+}
+
+#[test]
+fn test_populate() {
+    let result = TOXIPROXY.populate(vec![ProxyPack::new(
+        "socket".into(),
+        "localhost:2001".into(),
+        "localhost:2000".into(),
+    )]);
+
+    assert!(result.is_ok());
+
+    assert_eq!(1, result.as_ref().unwrap().len());
+    assert_eq!("socket", result.as_ref().unwrap()[0].proxy_pack.name);
+}
+
+#[test]
+fn test_populate_map() {
+    let result = TOXIPROXY.populate_map(vec![
+        ProxyPack::new(
+            "socket".into(),
+            "localhost:2001".into(),
+            "localhost:2000".into(),
+        ),
+        ProxyPack::new(
+            "socket_other".into(),
+            "localhost:2002".into(),
+            "localhost:2000".into(),
+        ),
+    ]);
+    assert!(result.is_ok());
+
+    let proxies = result.unwrap();
+    assert_eq!(2, proxies.len());
+    assert_eq!(
+        "localhost:2000",
+        proxies["socket_other"].proxy_pack.upstream
+    );
+
+    assert!(proxies["socket_other"].delete().is_ok());
+}
+
+#[test]
+fn test_populate_if_changed() {
+    let socket = r#"{"socket":{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]}}"#;
+    let server = mock_server(vec![
+        (200, socket),
+        (200, socket),
+        (
+            201,
+            r#"{"proxies":[{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2002","enabled":true,"toxics":[]}]}"#,
+        ),
+    ]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let proxies = client
+        .populate_if_changed(vec![ProxyPack::new(
+            "socket".into(),
+            "localhost:2001".into(),
+            "localhost:2000".into(),
+        )])
+        .unwrap();
+    assert_eq!("socket", proxies[0].proxy_pack.name);
+    assert_eq!(1, server.requests.lock().unwrap().len());
+
+    let proxies = client
+        .populate_if_changed(vec![ProxyPack::new(
+            "socket".into(),
+            "localhost:2001".into(),
+            "localhost:2002".into(),
+        )])
+        .unwrap();
+    assert_eq!("localhost:2002", proxies[0].proxy_pack.upstream);
+
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(3, requests.len());
+    assert!(requests[2].starts_with("POST /populate "));
+}
+
+#[test]
+fn test_enabled_proxies() {
+    let server = mock_server(vec![(
+        200,
+        r#"{"live":{"name":"live","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]},
+            "down":{"name":"down","listen":"127.0.0.1:2002","upstream":"localhost:2000","enabled":false,"toxics":[]}}"#,
+    )]);
+
+    let proxies = client::Client::new(server.addr.as_str())
+        .enabled_proxies()
+        .unwrap();
+
+    assert_eq!(1, proxies.len());
+    assert_eq!("live", proxies[0].proxy_pack.name);
+}
+
+#[test]
+fn test_prune_orphans() {
+    let proxies = r#"{"socket":{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]},
+        "leaked_b":{"name":"leaked_b","listen":"127.0.0.1:2002","upstream":"localhost:2000","enabled":true,"toxics":[]},
+        "leaked_a":{"name":"leaked_a","listen":"127.0.0.1:2003","upstream":"localhost:2000","enabled":true,"toxics":[]}}"#;
+    let server = mock_server(vec![(200, proxies), (200, proxies), (204, ""), (204, "")]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let reported = client.prune_orphans(&["socket"], false);
+    let pruned = client.prune_orphans(&["socket"], true);
+
+    assert_eq!(vec!["leaked_a", "leaked_b"], reported.unwrap());
+    assert_eq!(vec!["leaked_a", "leaked_b"], pruned.unwrap());
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(4, requests.len());
+    assert!(requests[2].starts_with("DELETE /proxies/leaked_a "));
+    assert!(requests[3].starts_with("DELETE /proxies/leaked_b "));
+}
+
+#[test]
+fn test_proxy_labels() {
+    let proxies = r#"{"payments":{"name":"payments","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]},
+        "search":{"name":"search","listen":"127.0.0.1:2002","upstream":"localhost:2000","enabled":true,"toxics":[]}}"#;
+    let server = mock_server(vec![
+        (200, proxies),
+        (
+            200,
+            r#"{"name":"payments","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":false,"toxics":[]}"#,
+        ),
+    ]);
+    let mut proxies = client::Client::new(server.addr.as_str()).all().unwrap();
+
+    for (name, proxy) in proxies.iter_mut() {
+        proxy.set_label("env", "test");
+        proxy.set_label("service", name);
+    }
+    for proxy in proxies
+        .values()
+        .filter(|proxy| proxy.has_label("service", "payments"))
+    {
+        assert!(proxy.disable().is_ok());
+    }
+
+    assert_eq!(Some("test"), proxies["search"].label("env"));
+    assert_eq!(None, proxies["search"].label("team"));
+    assert_eq!(2, proxies["payments"].labels().len());
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(2, requests.len());
+    assert!(requests[1].starts_with("POST /proxies/payments "));
+}
+
+#[test]
+fn test_all_lenient() {
+    let server = mock_server(vec![(
+        200,
+        r#"{"socket":{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]},"future":{"name":"future","listen":{"host":"127.0.0.1"},"upstream":"localhost:2000"}}"#,
+    )]);
+
+    let proxies = client::Client::new(server.addr.as_str())
+        .all_lenient()
+        .unwrap();
+
+    assert_eq!(vec!["socket"], proxies.proxies.keys().collect::<Vec<_>>());
+    assert_eq!(vec!["future"], proxies.malformed.keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_export_metrics() {
+    let server = mock_server(vec![(
+        200,
+        r#"{"socket":{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[
+            {"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":1000}},
+            {"name":"latency_upstream","type":"latency","stream":"upstream","toxicity":1,"attributes":{"latency":1000}},
+            {"name":"timeout_upstream","type":"timeout","stream":"upstream","toxicity":1,"attributes":{"timeout":0}}]},
+            "db":{"name":"db","listen":"127.0.0.1:2002","upstream":"localhost:5432","enabled":false,"toxics":[]}}"#,
+    )]);
+
+    let metrics = client::Client::new(server.addr.as_str())
+        .export_metrics()
+        .unwrap();
+    let lines = metrics.lines().collect::<Vec<_>>();
+
+    assert!(lines.contains(&"# TYPE toxiproxy_proxy_enabled gauge"));
+    assert!(lines.contains(&r#"toxiproxy_proxy_enabled{proxy="socket"} 1"#));
+    assert!(lines.contains(&r#"toxiproxy_proxy_enabled{proxy="db"} 0"#));
+    assert!(lines.contains(&"# TYPE toxiproxy_active_toxics gauge"));
+    assert!(lines.contains(&r#"toxiproxy_active_toxics{proxy="socket",type="latency"} 2"#));
+    assert!(lines.contains(&r#"toxiproxy_active_toxics{proxy="socket",type="timeout"} 1"#));
+    assert!(!metrics.contains(r#"toxiproxy_active_toxics{proxy="db""#));
+}
+
+#[test]
+fn test_populate_listen_mismatch() {
+    let server = mock_server(vec![(
+        201,
+        r#"{"proxies":[{"name":"socket","listen":"127.0.0.1:2999","upstream":"localhost:2000","enabled":true,"toxics":[]}]}"#,
+    )]);
+
+    let result = client::Client::new(server.addr.as_str()).populate(vec![ProxyPack::new(
+        "socket".into(),
+        "localhost:2001".into(),
+        "localhost:2000".into(),
+    )]);
+
+    assert!(result.is_err());
+    assert!(result.err().unwrap().to_string().contains("127.0.0.1:2999"));
+    assert!(server.requests.lock().unwrap()[0].starts_with("POST /populate"));
+}
+
+#[test]
+fn test_create_proxy_listen_mismatch() {
+    let server = mock_server(vec![(
+        201,
+        r#"{"name":"redis","listen":"127.0.0.1:2999","upstream":"localhost:6379","enabled":true,"toxics":[]}"#,
+    )]);
+
+    let result = client::Client::new(server.addr.as_str()).create_proxy(
+        "redis",
+        "localhost:2001",
+        "localhost:6379",
+    );
+
+    assert_eq!(
+        "proxy `redis` listens on 127.0.0.1:2999 instead of the requested localhost:2001",
+        result.err().unwrap().to_string()
+    );
+    assert!(server.requests.lock().unwrap()[0].starts_with("POST /proxies "));
+}
+
+#[test]
+fn test_populate_missing_proxy() {
+    let server = mock_server(vec![(
+        201,
+        r#"{"proxies":[{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]}]}"#,
+    )]);
+
+    let result = client::Client::new(server.addr.as_str()).populate(vec![
+        ProxyPack::new(
+            "socket".into(),
+            "localhost:2001".into(),
+            "localhost:2000".into(),
+        ),
+        ProxyPack::new(
+            "unbound".into(),
+            "localhost:2002".into(),
+            "localhost:2000".into(),
+        ),
+    ]);
+
+    assert_eq!(
+        "populate has not created proxies: unbound",
+        result.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_clone_proxy() {
+    populate_example();
+    let source = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    source
+        .with_latency("downstream".into(), 1000, 100, 1.0)
+        .unwrap()
+        .with_bandwidth("upstream".into(), 50, 0.5)
+        .unwrap();
+
+    let copy = TOXIPROXY.clone_proxy("socket", "socket_copy", "localhost:2012");
+    assert!(source.delete_all_toxics().is_ok());
+    let copy = copy.unwrap();
+
+    assert_eq!("socket_copy", copy.proxy_pack.name);
+    assert_eq!(source.proxy_pack.upstream, copy.proxy_pack.upstream);
+
+    let toxics = copy.toxics().unwrap();
+    assert!(copy.delete().is_ok());
+    assert_eq!(2, toxics.len());
+    assert_eq!(2, copy.proxy_pack.toxics.len());
+    assert_eq!(
+        Some(&serde_json::json!(100)),
+        toxics[0].attributes.get("jitter")
+    );
+    assert_eq!("bandwidth", toxics[1].r#type);
+    assert_eq!(0.5, toxics[1].toxicity);
+}
+
+#[test]
+fn test_scenario() {
+    populate_example();
+    let socket = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    socket.with_bandwidth("upstream".into(), 50, 1.0).unwrap();
+    let db = TOXIPROXY
+        .create_proxy("scenario_db", "localhost:2014", "localhost:2000")
+        .unwrap();
+
+    let scenario = scenario::Scenario::new("flaky-db")
+        .with_toxic(
+            "socket",
+            toxic::ToxicBuilder::new("latency".into())
+                .attribute("latency".into(), 1000)
+                .build()
+                .unwrap(),
+        )
+        .with_toxic(
+            "scenario_db",
+            toxic::ToxicBuilder::new("timeout".into())
+                .attribute("timeout".into(), 100)
+                .build()
+                .unwrap(),
+        );
+    let applied = TOXIPROXY.apply_scenario(&scenario);
+    let reapplied = TOXIPROXY.apply_scenario(&scenario);
+    let during = (socket.toxics().unwrap(), db.toxics().unwrap());
+    let cleared = TOXIPROXY.clear_scenario(&scenario);
+    let after = (socket.toxics().unwrap(), db.toxics().unwrap());
+
+    assert!(socket.delete_all_toxics().is_ok());
+    assert!(db.delete().is_ok());
+
+    assert!(applied.is_ok());
+    assert!(reapplied.is_err());
+    assert_eq!(2, during.0.len());
+    assert_eq!(1, during.1.len());
+    assert_eq!("timeout", during.1[0].r#type);
+    assert!(cleared.is_ok());
+    assert_eq!(1, after.0.len());
+    assert_eq!("bandwidth", after.0[0].r#type);
+    assert!(after.1.is_empty());
+}
+
+#[test]
+fn test_clear_scenario_retries_failed_toxics() {
+    let latency = r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":1000}}"#;
+    let timeout = r#"{"name":"timeout_downstream","type":"timeout","stream":"downstream","toxicity":1,"attributes":{"timeout":100}}"#;
+    let server = mock_server(vec![
+        (200, latency),
+        (200, timeout),
+        (204, ""),
+        (500, r#"{"error":"internal error","status":500}"#),
+        (204, ""),
+    ]);
+    let client = client::Client::new(server.addr.as_str());
+    let scenario = scenario::Scenario::new("flaky")
+        .with_toxic(
+            "socket",
+            toxic::ToxicBuilder::new("latency".into())
+                .attribute("latency".into(), 1000)
+                .build()
+                .unwrap(),
+        )
+        .with_toxic(
+            "socket",
+            toxic::ToxicBuilder::new("timeout".into())
+                .attribute("timeout".into(), 100)
+                .build()
+                .unwrap(),
+        );
+
+    assert!(client.apply_scenario(&scenario).is_ok());
+    let failed = client.clear_scenario(&scenario);
+    let retried = client.clear_scenario(&scenario);
+    let cleared = client.clear_scenario(&scenario);
+
+    assert_eq!(
+        "server error 500: internal error",
+        failed.err().unwrap().to_string()
+    );
+    assert!(retried.is_ok());
+    assert!(cleared.is_err());
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(5, requests.len());
+    assert!(requests[4].starts_with("DELETE /proxies/socket/toxics/timeout_downstream "));
+}
+
+#[test]
+fn test_clone_proxy_missing_source() {
+    let result = TOXIPROXY.clone_proxy("missing", "missing_copy", "localhost:2013");
+
+    assert!(matches!(
+        result.err().unwrap(),
+        error::ToxiproxyError::Server { status: 404, message } if message.contains("`missing`")
+    ));
+    assert!(TOXIPROXY.find_proxy("missing_copy").is_err());
+}
+
+#[test]
+fn test_create_proxy() {
+    let created = TOXIPROXY.create_proxy("created", "127.0.0.1:0", "localhost:2000");
+    let found = TOXIPROXY.find_proxy("created");
+    let conflict = TOXIPROXY.create_proxy("created", "127.0.0.1:0", "localhost:2000");
+    assert!(created.unwrap().delete().is_ok());
+
+    assert_eq!("localhost:2000", found.unwrap().proxy_pack.upstream);
+    assert!(conflict
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("server error 409: "));
+}
+
+#[test]
+fn test_create_proxy_unique() {
+    let first = TOXIPROXY.create_proxy_unique("unique", "127.0.0.1:0", "localhost:2000");
+    let second = TOXIPROXY.create_proxy_unique("unique", "127.0.0.1:0", "localhost:2000");
+    let (first, second) = (first.unwrap(), second.unwrap());
+    assert!(first.delete().is_ok());
+    assert!(second.delete().is_ok());
+
+    assert!(first.proxy_pack.name.starts_with("unique_"));
+    assert!(second.proxy_pack.name.starts_with("unique_"));
+    assert_ne!(first.proxy_pack.name, second.proxy_pack.name);
+}
+
+#[test]
+fn test_populate_with_seed() {
+    let server = mock_server(vec![(
+        201,
+        r#"{"proxies":[{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]},
+            {"name":"unseeded","listen":"127.0.0.1:2002","upstream":"localhost:2000","enabled":true,"toxics":[]}]}"#,
+    )]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let result = client.populate(vec![
+        ProxyPack::new(
+            "socket".into(),
+            "localhost:2001".into(),
+            "localhost:2000".into(),
+        )
+        .with_seed(42),
+        ProxyPack::new(
+            "unseeded".into(),
+            "localhost:2002".into(),
+            "localhost:2000".into(),
+        ),
+    ]);
+    assert!(result.is_ok());
+
+    let body: serde_json::Value = serde_json::from_str(&server.bodies.lock().unwrap()[0]).unwrap();
+    assert_eq!(serde_json::json!(42), body[0]["rand_seed"]);
+    assert!(body[1].get("rand_seed").is_none());
+}
+
+#[test]
+fn test_fault_report() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 2000, 10, 0.5)
+        .unwrap();
+    let report = TOXIPROXY.fault_report();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    let report = report.unwrap();
+    assert!(report.contains("socket (enabled): "));
+    assert!(report.contains(" -> localhost:2000\n"));
+    assert!(report.contains(
+        "  latency_downstream: latency on downstream, toxicity 0.5, {\"jitter\":10,\"latency\":2000}\n"
+    ));
+}
+
+#[test]
+fn test_all() {
+    populate_example();
+
+    let result = TOXIPROXY.all();
+    assert!(result.is_ok());
+    assert_eq!(1, result.as_ref().unwrap().len());
+}
+
+#[test]
+fn test_debug_redacts_client() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let toxics = proxy
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap()
+        .toxics()
+        .unwrap();
+
+    let proxy_debug = format!("{:?}", proxy);
+    assert!(proxy_debug.contains("\"socket\""));
+    assert!(proxy_debug.contains("<client>"));
+    assert!(!proxy_debug.contains("HttpClient"));
+    assert!(!proxy_debug.contains("8474"));
+
+    let toxic_debug = format!("{:?}", toxics[0]);
+    assert!(toxic_debug.contains("\"latency_downstream\""));
+    assert!(!toxic_debug.contains("HttpClient"));
+    assert!(!toxic_debug.contains("8474"));
+}
+
+#[test]
+fn test_delete_all_proxies() {
+    let proxy_packs = (0..12)
+        .map(|i| {
+            ProxyPack::new(
+                format!("socket_{}", i),
+                format!("localhost:{}", 3000 + i),
+                "localhost:2000".into(),
+            )
+        })
+        .collect();
+    assert!(TOXIPROXY.populate(proxy_packs).is_ok());
+
+    assert!(TOXIPROXY.delete_all_proxies(4).is_ok());
+
+    let result = TOXIPROXY.all();
+    assert!(result.is_ok());
+    assert_eq!(0, result.as_ref().unwrap().len());
+}
+
+#[test]
+fn test_metrics_summary() {
+    let server = mock_server(vec![(
+        200,
+        r#"{
+            "socket":{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[],
+                "stats":{"connections":3,"bytes_sent":100,"bytes_received":40}},
+            "redis":{"name":"redis","listen":"127.0.0.1:2002","upstream":"localhost:6379","enabled":true,"toxics":[],
+                "stats":{"connections":2,"bytes_sent":50}},
+            "plain":{"name":"plain","listen":"127.0.0.1:2003","upstream":"localhost:2000","enabled":true,"toxics":[]}
+        }"#,
+    )]);
+
+    let summary = client::Client::new(server.addr.as_str()).metrics_summary();
+    assert!(summary.is_ok());
+
+    let summary = summary.unwrap();
+    assert_eq!(3, summary.proxies.len());
+    assert_eq!(5, summary.total_connections);
+    assert_eq!(150, summary.total_bytes_sent);
+    assert_eq!(40, summary.total_bytes_received);
+    assert_eq!(None, summary.proxies["redis"].bytes_received);
+    assert_eq!(metrics::ProxyMetrics::default(), summary.proxies["plain"]);
+}
+
+#[test]
+fn test_version() {
+    assert!(TOXIPROXY.version().is_ok());
+}
+
+#[test]
+fn test_version_unparsable() {
+    let server = mock_server(vec![(200, "dev-build")]);
+
+    let version = client::Client::new(server.addr.as_str()).version();
+    assert!(version.is_ok());
+    assert_eq!("dev-build", version.as_ref().unwrap().raw);
+    assert_eq!(None, version.as_ref().unwrap().parsed);
+}
+
+#[test]
+fn test_version_parsed() {
+    let server = mock_server(vec![(200, "2.1.4"), (200, r#"{"version":"v2.5.0"}"#)]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let expected = version::Version {
+        major: 2,
+        minor: 1,
+        patch: 4,
+    };
+    assert_eq!(Some(expected), client.version().unwrap().parsed);
+
+    let version = client.version().unwrap();
+    assert_eq!(r#"{"version":"v2.5.0"}"#, version.raw);
+    assert_eq!("2.5.0", version.parsed.unwrap().to_string());
+}
+
+#[test]
+fn test_supported_toxic_types() {
+    let server = mock_server(vec![
+        (200, "2.1.4"),
+        (200, r#"{"version":"v2.5.0"}"#),
+        (200, "dev-build"),
+    ]);
+    let client = client::Client::new(server.addr.as_str());
+
+    let old = client.supported_toxic_types().unwrap();
+    assert!(old.contains(&"limit_data".to_owned()));
+    assert!(!old.contains(&"reset_peer".to_owned()));
+    assert!(client
+        .supported_toxic_types()
+        .unwrap()
+        .contains(&"reset_peer".to_owned()));
+    assert_eq!(7, client.supported_toxic_types().unwrap().len());
+}
+
+#[test]
+fn test_server_error_message() {
+    let server = mock_server(vec![
+        (404, r#"{"error":"proxy not found","status":404}"#),
+        (500, "internal failure\n"),
+    ]);
+    let client = client::Client::new(server.addr.as_str());
+
+    assert_eq!(
+        "server error 404: proxy not found",
+        client.find_proxy("missing").err().unwrap().to_string()
+    );
+    assert_eq!(
+        "server error 500: internal failure",
+        client.find_proxy("missing").err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_version_chunked() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_thread = spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut [0u8; 1024]);
+        for part in &[
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            "2\r\n2.\r\n",
+            "3\r\n1.4\r\n",
+            "0\r\n\r\n",
+        ] {
+            stream.write_all(part.as_bytes()).unwrap();
+            stream.flush().unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    let version = client::Client::new(addr).version();
+    server_thread.join().expect("Failed closing server thread");
+
+    assert_eq!("2.1.4", version.unwrap().raw);
+}
+
+#[test]
+fn test_find_and_reset_proxy() {
+    populate_example();
+
+    let result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(result.is_ok());
+
+    assert_eq!("socket", result.as_ref().unwrap().proxy_pack.name);
+}
+
+#[test]
+fn test_find_and_reset_proxy_invalid() {
+    let result = TOXIPROXY.find_and_reset_proxy("bad-proxy");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_proxy_handle() {
+    let server = mock_server(vec![(
+        200,
+        r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":1000,"jitter":0}}"#,
+    )]);
+
+    client::Client::new(server.addr.as_str())
+        .proxy_handle("socket")
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(1, requests.len());
+    assert!(requests[0].starts_with("POST /proxies/socket/toxics "));
+}
+
+#[test]
+fn test_proxy_down() {
+    populate_example();
+
+    let result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(result.is_ok());
+    assert!(result.as_ref().unwrap().proxy_pack.enabled);
+
+    assert!(result
+        .as_ref()
+        .unwrap()
+        .with_down(|| {
+            let result = TOXIPROXY.find_and_reset_proxy("socket");
+            assert!(result.is_ok());
+            assert!(!result.as_ref().unwrap().proxy_pack.enabled);
+        })
+        .is_ok());
+
+    let result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(result.is_ok());
+    assert!(result.as_ref().unwrap().proxy_pack.enabled);
+}
+
+#[test]
+fn test_proxy_simulate_outage() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let mut refused = false;
+    let result = proxy.simulate_outage(|| {
+        refused = TcpStream::connect(&proxy.proxy_pack.listen).is_err();
+    });
+    let server_thread = echo_server();
+    let echo_result = proxy.echo_roundtrip(b"hello");
+    server_thread.join().expect("Failed closing server thread");
+
+    assert!(result.is_ok());
+    assert!(refused);
+    assert!(TOXIPROXY.find_proxy("socket").unwrap().proxy_pack.enabled);
+    assert_eq!(b"hello".to_vec(), echo_result.unwrap());
+}
+
+#[test]
+fn test_proxy_simulate_timeout() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let server_thread = echo_server();
+    let mut hung = None;
+    let result = proxy.simulate_timeout(|| {
+        let mut stream = TcpStream::connect(&proxy.proxy_pack.listen).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(300)))
+            .unwrap();
+        stream.write_all(b"hello").unwrap();
+        hung = stream.read(&mut [0u8; 16]).err().map(|err| err.kind());
+    });
+    server_thread.join().expect("Failed closing server thread");
+    let toxics = proxy.toxics();
+    let server_thread = echo_server();
+    let echo_result = proxy.echo_roundtrip(b"hello");
+    server_thread.join().expect("Failed closing server thread");
+
+    assert!(result.is_ok());
+    assert!(matches!(
+        hung,
+        Some(std::io::ErrorKind::WouldBlock) | Some(std::io::ErrorKind::TimedOut)
+    ));
+    assert!(toxics.unwrap().is_empty());
+    assert_eq!(b"hello".to_vec(), echo_result.unwrap());
+}
+
+#[test]
+fn test_proxy_apply_with_latency() {
+    populate_example();
+
+    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(proxy_result.is_ok());
+
+    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
+    assert!(proxy_toxics.is_ok());
+    assert_eq!(0, proxy_toxics.as_ref().unwrap().len());
+
+    let apply_result = proxy_result
+        .as_ref()
+        .unwrap()
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap()
+        .apply(|| {
+            let all = TOXIPROXY.all();
+            assert!(all.is_ok());
+            let proxy = all.as_ref().unwrap().get("socket");
+            assert!(proxy.is_some());
+
+            let proxy_toxics = proxy.as_ref().unwrap().toxics();
+            assert!(proxy_toxics.is_ok());
+            assert_eq!(1, proxy_toxics.as_ref().unwrap().len());
+        });
+
+    assert!(apply_result.is_ok());
+
+    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
+    assert!(proxy_toxics.is_ok());
+    assert_eq!(0, proxy_toxics.as_ref().unwrap().len());
+}
+
+#[test]
+fn test_proxy_with_latency_asymmetric() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let result = proxy
+        .with_latency_asymmetric((10, 0, 0.5), (2000, 100, 1.0))
+        .map(|_| ());
+    let upstream = proxy.get_toxic("latency_upstream");
+    let downstream = proxy.get_toxic("latency_downstream");
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(result.is_ok());
+    let upstream = upstream.unwrap().unwrap();
+    assert_eq!(Some("upstream"), upstream.stream.as_deref());
+    assert_eq!(Some(10), upstream.integer_attribute("latency"));
+    assert_eq!(Some(0), upstream.integer_attribute("jitter"));
+    assert_eq!(0.5, upstream.toxicity);
+    let downstream = downstream.unwrap().unwrap();
+    assert_eq!(Some("downstream"), downstream.stream.as_deref());
+    assert_eq!(Some(2000), downstream.integer_attribute("latency"));
+    assert_eq!(Some(100), downstream.integer_attribute("jitter"));
+    assert_eq!(1.0, downstream.toxicity);
+}
+
+#[test]
+fn test_proxy_with_latency_asymmetric_rolls_back() {
+    let upstream = r#"{"name":"latency_upstream","type":"latency","stream":"upstream","toxicity":1,"attributes":{"latency":10,"jitter":0}}"#;
+    let server = mock_server(vec![
+        (200, upstream),
+        (500, r#"{"error":"internal error","status":500}"#),
+        (204, ""),
+    ]);
+
+    let result = client::Client::new(server.addr.as_str())
+        .proxy_handle("socket")
+        .with_latency_asymmetric((10, 0, 1.0), (2000, 100, 1.0))
+        .map(|_| ());
+
+    assert_eq!(
+        "server error 500: internal error",
+        result.err().unwrap().to_string()
+    );
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(3, requests.len());
+    assert!(requests[2].starts_with("DELETE /proxies/socket/toxics/latency_upstream "));
+}
+
+#[test]
+fn test_proxy_apply_server_went_away() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = client::Client::new(listener.local_addr().unwrap()).proxy_handle("socket");
+
+    let result = proxy.apply(|| drop(listener));
+
+    let err = result.err().unwrap();
+    assert!(matches!(err, error::ToxiproxyError::ServerWentAway(_)));
+    assert!(err.to_string().starts_with("server went away: "));
+}
+
+#[test]
+fn test_proxy_with_latency_unreachable() {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let proxy = client::Client::new(addr).proxy_handle("socket");
+
+    let result = proxy.with_latency("downstream".into(), 1000, 0, 1.0);
+
+    assert!(matches!(result, Err(error::ToxiproxyError::Http(_))));
+}
+
+#[test]
+fn test_proxy_add_toxic() {
+    let server = mock_server(vec![
+        (
+            200,
+            r#"{"name":"server_named","type":"bandwidth","stream":"upstream","toxicity":1,"attributes":{"rate":50}}"#,
+        ),
+        (
+            200,
+            r#"{"name":"server_named","type":"bandwidth","stream":"upstream","toxicity":0.5,"attributes":{"rate":50}}"#,
+        ),
+    ]);
+    let proxy = client::Client::new(server.addr.as_str()).proxy_handle("socket");
+
+    let toxic = proxy
+        .add_toxic(
+            toxic::ToxicBuilder::new("bandwidth".into())
+                .stream("upstream".into())
+                .attribute("rate".into(), 50)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!("server_named", toxic.name);
+    assert!(toxic.set_toxicity(0.5).is_ok());
+
+    let requests = server.requests.lock().unwrap();
+    assert!(requests[0].starts_with("POST /proxies/socket/toxics "));
+    assert!(requests[1].starts_with("POST /proxies/socket/toxics/server_named "));
+}
+
+#[test]
+fn test_proxy_add_toxic_with_timeout() {
+    // Accepts connections but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let http_client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap();
+    let proxy = client::Client::with_client(listener.local_addr().unwrap(), http_client)
+        .proxy_handle("socket");
+    let toxic = toxic::ToxicBuilder::new("latency".into())
+        .attribute("latency".into(), 1000)
+        .build()
+        .unwrap();
+
+    let start = SystemTime::now();
+    let result = proxy.add_toxic_with_timeout(toxic, Duration::from_millis(200));
+
+    assert!(result.is_err());
+    assert!(start.elapsed().unwrap() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_proxy_apply_deletes_by_toxic_path() {
+    let server = mock_server(vec![
+        (
+            200,
+            r#"[{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":2000,"jitter":0}}]"#,
+        ),
+        (204, ""),
+    ]);
+
+    let result = client::Client::new(server.addr.as_str())
+        .proxy_handle("socket")
+        .apply(|| {});
+
+    assert!(result.is_ok());
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(2, requests.len());
+    assert!(requests[1].starts_with("DELETE /proxies/socket/toxics/latency_downstream "));
+}
+
+#[test]
+fn test_proxy_apply_with_latency_as_separate_calls_for_test() {
+    populate_example();
+
+    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(proxy_result.is_ok());
+
+    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
+    assert!(proxy_toxics.is_ok());
+    assert_eq!(0, proxy_toxics.as_ref().unwrap().len());
+
+    let _ = proxy_result
+        .as_ref()
+        .unwrap()
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap();
+
+    let all = TOXIPROXY.all();
+    assert!(all.is_ok());
+    let proxy = all.as_ref().unwrap().get("socket");
+    assert!(proxy.is_some());
+
+    let proxy_toxics = proxy.as_ref().unwrap().toxics();
+    assert!(proxy_toxics.is_ok());
+    assert_eq!(1, proxy_toxics.as_ref().unwrap().len());
+}
+
+#[test]
+fn test_proxy_ensure_latency() {
+    populate_example();
+
+    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(proxy_result.is_ok());
+
+    let ensured = proxy_result
+        .as_ref()
+        .unwrap()
+        .ensure_latency("downstream".into(), 1000, 0, 1.0)
+        .and_then(|proxy| proxy.ensure_latency("downstream".into(), 2000, 0, 1.0))
+        .map(|_| ());
+    assert!(ensured.is_ok());
+
+    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
+    assert!(proxy_toxics.is_ok());
+    assert_eq!(1, proxy_toxics.as_ref().unwrap().len());
+    assert_eq!(
+        Some(&serde_json::json!(2000)),
+        proxy_toxics.as_ref().unwrap()[0].attributes.get("latency")
+    );
+}
+
+#[test]
+fn test_proxy_default_toxicity() {
+    populate_example();
+
+    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(proxy_result.is_ok());
+
+    let mut proxy = proxy_result.unwrap();
+    assert_eq!(1.0, proxy.default_toxicity());
+    proxy.set_default_toxicity(0.5);
+
+    proxy
+        .with_latency_default("downstream".into(), 1000, 0)
+        .unwrap()
+        .with_latency("upstream".into(), 1000, 0, 0.25)
+        .unwrap();
+
+    let proxy_toxics = proxy.toxics().unwrap();
+    let toxicity_of = |stream: &str| {
+        proxy_toxics
+            .iter()
+            .find(|toxic| toxic.stream.as_deref() == Some(stream))
+            .map(|toxic| toxic.toxicity)
+    };
+    assert_eq!(Some(0.5), toxicity_of("downstream"));
+    assert_eq!(Some(0.25), toxicity_of("upstream"));
+
+    assert!(proxy.delete_all_toxics().is_ok());
+}
+
+#[test]
+fn test_proxy_toxics_order() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_slicer("downstream".into(), 10, 0, 0, 1.0)
+        .unwrap()
+        .with_bandwidth("upstream".into(), 100, 1.0)
+        .unwrap()
+        .with_latency("downstream".into(), 10, 0, 1.0)
+        .unwrap();
+
+    let names = |toxics: Vec<toxic::ToxicPack>| {
+        toxics
+            .into_iter()
+            .map(|toxic| toxic.name)
+            .collect::<Vec<_>>()
+    };
+    let all = names(proxy.toxics().unwrap());
+    let chain = names(proxy.toxics_on_stream("downstream").unwrap());
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert_eq!(
+        vec![
+            "slicer_downstream",
+            "bandwidth_upstream",
+            "latency_downstream"
+        ],
+        all
+    );
+    assert_eq!(vec!["slicer_downstream", "latency_downstream"], chain);
+}
+
+#[test]
+fn test_proxy_toxics_are_bound() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap()
+        .with_bandwidth("upstream".into(), 100, 1.0)
+        .unwrap();
+
+    let toxics = proxy.toxics().unwrap();
+    let updated = toxics[0].set_toxicity(0.5);
+    let deleted = toxics[1].delete();
+    let remaining = proxy.toxics().unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert_eq!(
+        vec!["latency_downstream", "bandwidth_upstream"],
+        toxics.iter().map(|toxic| &toxic.name).collect::<Vec<_>>()
+    );
+    assert_eq!(0.5, updated.unwrap().toxicity);
+    assert!(deleted.is_ok());
+    assert_eq!(1, remaining.len());
+    assert_eq!(0.5, remaining[0].toxicity);
+}
+
+#[test]
+fn test_get_proxy() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+
+    let fetched = TOXIPROXY.get_proxy("socket");
+    let missing = TOXIPROXY.get_proxy("missing");
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    let fetched = fetched.unwrap().unwrap();
+    assert_eq!("localhost:2000", fetched.proxy_pack.upstream);
+    assert_eq!(1, fetched.proxy_pack.toxics.len());
+    assert_eq!("latency_downstream", fetched.proxy_pack.toxics[0].name);
+    assert!(missing.unwrap().is_none());
+}
+
+#[test]
+fn test_proxy_update_fields() {
+    let mut proxy = TOXIPROXY
+        .create_proxy_unique("update_fields", "127.0.0.1:0", "localhost:2000")
+        .unwrap();
+
+    let updated = proxy.update_fields(None, Some("localhost:2002"), Some(false));
+    let fetched = TOXIPROXY.get_proxy(&proxy.proxy_pack.name);
+    assert!(proxy.delete().is_ok());
+
+    assert!(updated.is_ok());
+    assert_eq!("localhost:2002", proxy.proxy_pack.upstream);
+    assert!(!proxy.proxy_pack.enabled);
+    let fetched = fetched.unwrap().unwrap();
+    assert_eq!("localhost:2002", fetched.proxy_pack.upstream);
+    assert_eq!(proxy.proxy_pack.listen, fetched.proxy_pack.listen);
+    assert!(!fetched.proxy_pack.enabled);
+}
+
+#[test]
+fn test_toxic_delete() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap()
+        .with_bandwidth("downstream".into(), 100, 1.0)
+        .unwrap();
+
+    let deleted = proxy
+        .get_toxic("latency_downstream")
+        .unwrap()
+        .unwrap()
+        .delete();
+    let remaining = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(deleted.is_ok());
+    assert_eq!(
+        vec!["bandwidth_downstream"],
+        remaining
+            .unwrap()
+            .iter()
+            .map(|toxic| &toxic.name)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_toxic_guard() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy.with_bandwidth("downstream".into(), 100, 1.0).unwrap();
+
+    let (name, guarded) = {
+        let guard = proxy
+            .add_toxic_guarded(
+                toxic::ToxicBuilder::new("latency".into())
+                    .attribute("latency".into(), 1000)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        (guard.name().to_owned(), proxy.toxics())
+    };
+    let remaining = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert_eq!("latency_downstream", name);
+    assert_eq!(2, guarded.unwrap().len());
+    assert_eq!(
+        vec!["bandwidth_downstream"],
+        remaining
+            .unwrap()
+            .iter()
+            .map(|toxic| &toxic.name)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_toxic_guard_try_from() {
+    use std::convert::TryFrom;
+
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let timeout = || {
+        toxic::ToxicBuilder::new("timeout".into())
+            .attribute("timeout".into(), 100)
+            .build()
+            .unwrap()
+    };
+
+    let unbound = toxic::ToxicGuard::try_from(timeout());
+    let guarded = {
+        let _guard = toxic::ToxicGuard::try_from(proxy.replace_toxic(timeout()).unwrap()).unwrap();
+        proxy.toxics()
+    };
+    let remaining = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(unbound.is_err());
+    assert_eq!("timeout_downstream", guarded.unwrap()[0].name);
+    assert!(remaining.unwrap().is_empty());
+}
+
+#[test]
+fn test_proxy_get_toxic() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 50, 1.0)
+        .unwrap();
+
+    let toxic = proxy.get_toxic("latency_downstream");
+    let unknown = proxy.get_toxic("unknown");
+    let missing_proxy = TOXIPROXY
+        .proxy_handle("missing")
+        .get_toxic("latency_downstream");
+    let updated = toxic.as_ref().unwrap().as_ref().unwrap().set_toxicity(0.5);
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    let toxic = toxic.unwrap().unwrap();
+    assert_eq!("latency", toxic.r#type);
+    assert_eq!(Some(1000), toxic.integer_attribute("latency"));
+    assert_eq!(Some(50), toxic.integer_attribute("jitter"));
+    assert!(unknown.unwrap().is_none());
+    assert!(missing_proxy.is_err());
+    assert!(updated.is_ok());
+}
+
+#[test]
+fn test_toxic_update() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 100, 10, 1.0)
+        .unwrap();
+
+    let mut toxic = proxy.get_toxic("latency_downstream").unwrap().unwrap();
+    let mut attributes = std::collections::HashMap::new();
+    attributes.insert("latency".to_owned(), 2000);
+    let updated = toxic.update(0.5, attributes);
+    let fetched = proxy.get_toxic("latency_downstream");
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(updated.is_ok());
+    assert_eq!(Some(2000), toxic.integer_attribute("latency"));
+    assert_eq!(0.5, toxic.toxicity);
+    let fetched = fetched.unwrap().unwrap();
+    assert_eq!(Some(2000), fetched.integer_attribute("latency"));
+    assert_eq!(Some(10), fetched.integer_attribute("jitter"));
+    assert_eq!(0.5, fetched.toxicity);
+}
+
+#[test]
+fn test_toxic_update_keeps_other_attributes() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 10, 1.0)
+        .unwrap();
+
+    let mut toxic = proxy.get_toxic("latency_downstream").unwrap().unwrap();
+    let mut attributes = std::collections::HashMap::new();
+    attributes.insert("jitter".to_owned(), 50);
+    let updated = toxic.update(1.0, attributes);
+    let fetched = proxy.get_toxic("latency_downstream");
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(updated.is_ok());
+    let fetched = fetched.unwrap().unwrap();
+    assert_eq!(Some(1000), fetched.integer_attribute("latency"));
+    assert_eq!(Some(50), fetched.integer_attribute("jitter"));
+    assert_eq!(Some(1000), toxic.integer_attribute("latency"));
+}
+
+#[test]
+fn test_toxic_update_sends_merged_attributes() {
+    let toxic = r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":1000,"jitter":10}}"#;
+    let server = mock_server(vec![
+        (200, toxic),
+        (200, toxic),
+        (
+            200,
+            r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":1000,"jitter":50}}"#,
+        ),
+    ]);
+    let mut toxic = client::Client::new(server.addr.as_str())
+        .proxy_handle("socket")
+        .get_toxic("latency_downstream")
+        .unwrap()
+        .unwrap();
+
+    let mut attributes = std::collections::HashMap::new();
+    attributes.insert("jitter".to_owned(), 50);
+    let updated = toxic.update(1.0, attributes);
+
+    assert!(updated.is_ok());
+    let requests = server.requests.lock().unwrap();
+    assert!(requests[1].starts_with("GET /proxies/socket/toxics/latency_downstream "));
+    assert!(requests[2].starts_with("POST /proxies/socket/toxics/latency_downstream "));
+    let body: serde_json::Value = serde_json::from_str(&server.bodies.lock().unwrap()[2]).unwrap();
+    assert_eq!(
+        serde_json::json!({ "latency": 1000, "jitter": 50 }),
+        body["attributes"]
+    );
+}
+
+#[test]
+fn test_proxy_diff_toxics() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap()
+        .with_bandwidth("upstream".into(), 50, 1.0)
+        .unwrap();
+
+    let expected = vec![
+        toxic::ToxicBuilder::new("latency".into())
+            .attribute("latency".into(), 2000)
+            .build()
+            .unwrap(),
+        toxic::ToxicBuilder::new("bandwidth".into())
+            .stream("upstream".into())
+            .attribute("rate".into(), 50)
+            .build()
+            .unwrap(),
+    ];
+    let diff = proxy.diff_toxics(&expected).unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(!diff.is_empty());
+    assert!(diff.missing.is_empty());
+    assert!(diff.extra.is_empty());
+    assert_eq!(1, diff.mismatched.len());
+    assert_eq!(
+        Some(&serde_json::json!(2000)),
+        diff.mismatched[0].expected.attributes.get("latency")
+    );
+    assert_eq!(
+        Some(&serde_json::json!(1000)),
+        diff.mismatched[0].active.attributes.get("latency")
+    );
+}
+
+#[test]
+fn test_proxy_watch_toxics() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let (changes, changed) = std::sync::mpsc::channel();
+    let watch = proxy
+        .watch_toxics(Duration::from_millis(50), move |toxics| {
+            let _ = changes.send(toxics.to_vec());
+        })
+        .unwrap();
+
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+    let added = changed.recv_timeout(Duration::from_secs(5));
+    assert!(proxy.delete_all_toxics().is_ok());
+    let removed = changed.recv_timeout(Duration::from_secs(5));
+    watch.stop();
+
+    assert_eq!("latency_downstream", added.unwrap()[0].name);
+    assert!(removed.unwrap().is_empty());
+}
+
+#[test]
+fn test_proxy_debug_snapshot() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap();
+
+    let snapshot = proxy.debug_snapshot();
+    assert!(snapshot.is_ok());
+
+    let snapshot = snapshot.unwrap();
+    assert!(snapshot.contains("\"name\": \"socket\""));
+    assert!(snapshot.contains("\"name\": \"latency_downstream\""));
+    assert!(snapshot.contains("\"toxic_count\": 1"));
+}
+
+#[test]
+fn test_proxy_configure_then_enable() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let result = proxy.configure_then_enable(|proxy| {
+        assert!(!TOXIPROXY.find_proxy("socket").unwrap().proxy_pack.enabled);
+        proxy.with_latency("downstream".into(), 1000, 0, 1.0)?;
+        Ok(())
+    });
+    assert!(result.is_ok());
+
+    let configured = TOXIPROXY.find_proxy("socket").unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+    assert!(configured.proxy_pack.enabled);
+    assert_eq!(1, configured.proxy_pack.toxics.len());
+}
+
+#[test]
+fn test_proxy_configure_then_enable_failure() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let failed = proxy.configure_then_enable(|proxy| {
+        proxy
+            .with_latency("downstream".into(), 1000, 0, 1.0)?
+            .with_latency("downstream".into(), 2000, 0, 1.0)?;
+        Ok(())
+    });
+    let after_failure = TOXIPROXY.find_proxy("socket").unwrap();
+
+    let panicked = std::panic::catch_unwind(|| {
+        proxy
+            .configure_then_enable(|_| panic!("configuration panicked"))
+            .unwrap();
+    });
+    let after_panic = TOXIPROXY.find_proxy("socket").unwrap();
+    assert!(TOXIPROXY.find_and_reset_proxy("socket").is_ok());
+
+    assert!(failed.is_err());
+    assert!(!after_failure.proxy_pack.enabled);
+    assert!(panicked.is_err());
+    assert!(after_panic.proxy_pack.enabled);
+}
+
+#[test]
+fn test_ruby_style_aliases() {
+    populate_example();
+
+    let proxy = TOXIPROXY.get("socket");
+    assert!(proxy.is_ok());
+    assert_eq!("socket", proxy.as_ref().unwrap().proxy_pack.name);
+    assert!(proxy.as_ref().unwrap().delete_all_toxics().is_ok());
+
+    let mut attributes = std::collections::HashMap::new();
+    attributes.insert("rate".into(), 500);
+    let added =
+        proxy
+            .as_ref()
+            .unwrap()
+            .toxic("bandwidth".into(), "upstream".into(), 1.0, attributes);
+    assert!(added.is_ok());
+
+    let proxy_toxics = proxy.as_ref().unwrap().toxics();
+    assert!(proxy_toxics.is_ok());
+    assert_eq!(1, proxy_toxics.as_ref().unwrap().len());
+    assert_eq!("bandwidth_upstream", proxy_toxics.as_ref().unwrap()[0].name);
+
+    assert!(proxy
+        .as_ref()
+        .unwrap()
+        .down(|| {
+            assert!(!TOXIPROXY.get("socket").unwrap().proxy_pack.enabled);
+        })
+        .is_ok());
+    assert!(TOXIPROXY.get("socket").unwrap().proxy_pack.enabled);
+}
+
+#[test]
+fn test_toxic_set_latency_and_jitter() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let toxics = proxy
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap()
+        .toxics()
+        .unwrap();
+
+    let toxic = toxics[0].set_latency(1500);
+    assert!(toxic.is_ok());
+    assert_eq!(
+        Some(&serde_json::json!(1500)),
+        toxic.as_ref().unwrap().attributes.get("latency")
+    );
+
+    let toxic = toxic.unwrap().set_jitter(100);
+    assert!(toxic.is_ok());
+
+    let toxics = proxy.toxics().unwrap();
+    assert_eq!(
+        Some(&serde_json::json!(1500)),
+        toxics[0].attributes.get("latency")
+    );
+    assert_eq!(
+        Some(&serde_json::json!(100)),
+        toxics[0].attributes.get("jitter")
+    );
+}
+
+#[test]
+fn test_toxic_with_custom_name() {
+    let server = mock_server(vec![
+        (
+            200,
+            r#"{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]}"#,
+        ),
+        (
+            200,
+            r#"[{"name":"my_custom_latency","type":"latency","stream":"upstream","toxicity":0.8,"attributes":{"latency":300,"jitter":0}}]"#,
+        ),
+        (
+            200,
+            r#"{"name":"my_custom_latency","type":"latency","stream":"upstream","toxicity":0.8,"attributes":{"latency":500,"jitter":0}}"#,
+        ),
+    ]);
+
+    let client = client::Client::new(server.addr.as_str());
+    let toxics = client.find_proxy("socket").unwrap().toxics().unwrap();
+
+    assert_eq!(1, toxics.len());
+    assert_eq!("my_custom_latency", toxics[0].name);
+    assert_eq!("latency", toxics[0].r#type);
+    assert_eq!(Some("upstream"), toxics[0].stream.as_deref());
+
+    assert!(toxics[0].set_latency(500).is_ok());
+    assert!(server.requests.lock().unwrap()[2]
+        .starts_with("POST /proxies/socket/toxics/my_custom_latency "));
+}
+
+#[test]
+fn test_proxy_set_all_toxicity() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 0.5)
+        .unwrap()
+        .with_bandwidth("upstream".into(), 50, 0.25)
+        .unwrap();
+
+    let updated = proxy.set_all_toxicity(1.0);
+    let toxics = proxy.toxics().unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert_eq!(2, updated.unwrap());
+    assert_eq!(2, toxics.len());
+    assert!(toxics.iter().all(|toxic| toxic.toxicity == 1.0));
+}
+
+#[test]
+fn test_mirrored_toxic_install() {
+    let proxy_response = r#"{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,"toxics":[]}"#;
+    let toxic_response = r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":2000,"jitter":0}}"#;
+    let primary = mock_server(vec![(200, proxy_response), (200, toxic_response)]);
+    let secondary = mock_server(vec![(200, proxy_response), (200, toxic_response)]);
+
+    let client = mirror::MirroredClient::new(
+        client::Client::new(primary.addr.as_str()),
+        client::Client::new(secondary.addr.as_str()),
+    );
+
+    let result = client.with_proxy("socket", |proxy| {
+        proxy.with_latency("downstream".into(), 2000, 0, 1.0)?;
+        Ok(())
+    });
+    assert!(result.is_ok());
+
+    for server in [primary, secondary].iter() {
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(2, requests.len());
+        assert!(requests[1].starts_with("POST /proxies/socket/toxics"));
+    }
+}
+
+#[test]
+fn test_partial_timeout_warns() {
+    populate_example();
+
+    let warnings = captured_logs();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy.with_timeout("downstream".into(), 0, 0.5).unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+    proxy
+        .add_toxic(
+            toxic::ToxicBuilder::new("timeout".into())
+                .stream("upstream".into())
+                .attribute("timeout".into(), 300)
+                .toxicity(0.25)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    let warnings = warnings.lock().unwrap();
+    assert!(warnings.iter().any(
+        |warning| warning.contains("timeout toxic on proxy `socket`") && warning.contains("hang")
+    ));
+    assert!(warnings.iter().any(|warning| warning
+        .contains("timeout toxic on proxy `socket` has a toxicity of 0.25")
+        && warning.contains("be closed after 300ms")));
+}
+
+#[test]
+fn test_proxy_with_latency_and_drain() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let server_thread = echo_server();
+    let mut open = TcpStream::connect("localhost:2001").unwrap();
+    open.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    open.write_all(b"ping").unwrap();
+    open.read_exact(&mut [0; 4]).unwrap();
+
+    let drained = proxy.with_latency_and_drain("downstream".into(), 300, 0, 1.0);
+    let read = open.read(&mut [0; 4]);
+    server_thread.join().expect("Failed closing server thread");
+
+    let server_thread = echo_server();
+    let mut reconnected = TcpStream::connect("localhost:2001").unwrap();
+    reconnected
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let start = SystemTime::now();
+    reconnected.write_all(b"ping").unwrap();
+    reconnected.read_exact(&mut [0; 4]).unwrap();
+    let elapsed = start.elapsed().unwrap();
+    drop(reconnected);
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
+
+    assert!(drained.is_ok());
+    assert!(matches!(read, Ok(0) | Err(_)));
+    assert!(elapsed >= Duration::from_millis(300));
+}
+
+#[test]
+fn test_proxy_with_latency_for() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let transient = proxy
+        .with_latency_for(
+            "downstream".into(),
+            2000,
+            0,
+            1.0,
+            Duration::from_millis(300),
+        )
+        .unwrap();
+    assert_eq!(1, proxy.toxics().unwrap().len());
+
+    assert!(transient.join().is_ok());
+    assert_eq!(0, proxy.toxics().unwrap().len());
+}
+
+#[test]
+fn test_proxy_with_latency_for_cancelled() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency_for(
+            "downstream".into(),
+            2000,
+            0,
+            1.0,
+            Duration::from_millis(300),
+        )
+        .unwrap()
+        .cancel();
+
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(1, proxy.toxics().unwrap().len());
+    assert!(proxy.delete_all_toxics().is_ok());
+}
+
+#[test]
+fn test_toxic_on_disabled_proxy_warns() {
+    populate_example();
+    let warnings = captured_logs();
+
+    assert!(TOXIPROXY
+        .find_and_reset_proxy("socket")
+        .unwrap()
+        .disable()
+        .is_ok());
+
+    let proxy = TOXIPROXY.find_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap();
+
+    assert!(warnings.lock().unwrap().iter().any(|warning| warning
+        == "toxic `latency_downstream` is added to proxy `socket` which is disabled"));
+
+    assert!(TOXIPROXY.find_and_reset_proxy("socket").is_ok());
+}
+
+#[test]
+fn test_proxy_remove_toxics_of_type() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap()
+        .with_latency("upstream".into(), 1000, 0, 1.0)
+        .unwrap()
+        .with_bandwidth("downstream".into(), 500, 1.0)
+        .unwrap();
+
+    let removed = proxy.remove_toxics_of_type("latency");
+    assert!(removed.is_ok());
+    assert_eq!(2, removed.unwrap());
+
+    let proxy_toxics = proxy.toxics().unwrap();
+    assert_eq!(1, proxy_toxics.len());
+    assert_eq!("bandwidth", proxy_toxics[0].r#type);
+}
+
+#[test]
+fn test_proxy_remove_latency() {
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("upstream".into(), 1000, 0, 1.0)
+        .unwrap()
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+
+    let removed = proxy.remove_latency("downstream");
+    let removed_again = proxy.remove_latency("downstream");
+    let toxics = proxy.toxics().unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert!(removed.is_ok());
+    assert_eq!(
+        "proxy `socket` has no `latency_downstream` toxic",
+        removed_again.err().unwrap().to_string()
+    );
+    assert_eq!(1, toxics.len());
+    assert_eq!("latency_upstream", toxics[0].name);
+}
+
+#[test]
+fn test_proxy_apply_with_latency_with_real_request() {
+    let server_thread = spawn(one_take_server);
+    populate_example();
 
-    assert!(result.is_ok());
+    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(proxy_result.is_ok());
 
-    assert_eq!(1, result.as_ref().unwrap().len());
-    assert_eq!("socket", result.as_ref().unwrap()[0].proxy_pack.name);
+    let apply_result = proxy_result
+        .as_ref()
+        .unwrap()
+        .with_latency("downstream".into(), 2000, 0, 1.0)
+        .unwrap()
+        .apply(|| {
+            let client_thread = spawn(one_shot_client);
+
+            server_thread.join().expect("Failed closing server thread");
+            let duration = client_thread.join().expect("Failed closing client thread");
+
+            assert!(duration.as_secs() >= 2);
+        });
+
+    assert!(apply_result.is_ok());
 }
 
 #[test]
-fn test_all() {
+fn test_proxy_echo_roundtrip_with_limit_data() {
+    let server_thread = echo_server();
     populate_example();
 
-    let result = TOXIPROXY.all();
-    assert!(result.is_ok());
-    assert_eq!(1, result.as_ref().unwrap().len());
+    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
+    assert!(proxy_result.is_ok());
+
+    let proxy = proxy_result.unwrap();
+    let echo_result = proxy
+        .with_limit_data("downstream".into(), 5, 1.0)
+        .unwrap()
+        .echo_roundtrip(b"hello world");
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
+
+    assert_eq!(b"hello".to_vec(), echo_result.unwrap());
 }
 
 #[test]
-fn test_version() {
-    assert!(TOXIPROXY.version().is_ok());
+fn test_proxy_with_bandwidth() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let payload = [7u8; 5000];
+
+    let server_thread = echo_server();
+    let start = SystemTime::now();
+    let unthrottled = proxy.echo_roundtrip(&payload);
+    let unthrottled_duration = start.elapsed().unwrap();
+    server_thread.join().expect("Failed closing server thread");
+
+    let added = proxy
+        .with_bandwidth("downstream".into(), 10, 1.0)
+        .map(|_| ());
+    let toxic = proxy.get_toxic("bandwidth_downstream");
+    let server_thread = echo_server();
+    let start = SystemTime::now();
+    let throttled = proxy.echo_roundtrip(&payload);
+    let throttled_duration = start.elapsed().unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
+
+    assert!(added.is_ok());
+    assert_eq!(Some(10), toxic.unwrap().unwrap().integer_attribute("rate"));
+    assert_eq!(payload.to_vec(), unthrottled.unwrap());
+    assert_eq!(payload.to_vec(), throttled.unwrap());
+    // 5000 bytes at 10 KB/s take about half a second.
+    assert!(throttled_duration >= Duration::from_millis(400));
+    assert!(throttled_duration > unthrottled_duration);
 }
 
 #[test]
-fn test_find_and_reset_proxy() {
+fn test_proxy_verify_latency_effect() {
     populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let without_toxic = proxy.verify_latency_effect("downstream", 300, 100);
 
-    let result = TOXIPROXY.find_and_reset_proxy("socket");
-    assert!(result.is_ok());
+    let server_thread = echo_server();
+    let verified = proxy
+        .with_latency("downstream".into(), 300, 0, 1.0)
+        .unwrap()
+        .verify_latency_effect("downstream", 300, 100);
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
 
-    assert_eq!("socket", result.as_ref().unwrap().proxy_pack.name);
+    assert!(without_toxic.is_err());
+    assert!(verified.unwrap());
 }
 
 #[test]
-fn test_find_and_reset_proxy_invalid() {
-    let result = TOXIPROXY.find_and_reset_proxy("bad-proxy");
-    assert!(result.is_err());
+fn test_proxy_verify_bandwidth_effect() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let without_toxic = proxy.verify_bandwidth_effect("downstream", 100, 30);
+
+    let server_thread = echo_server();
+    let verified = proxy
+        .with_bandwidth("downstream".into(), 100, 1.0)
+        .unwrap()
+        .verify_bandwidth_effect("downstream", 100, 30);
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
+
+    assert!(without_toxic.is_err());
+    assert!(verified.unwrap());
 }
 
 #[test]
-fn test_proxy_down() {
+fn test_proxy_with_timeout() {
     populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let toxic = proxy.with_timeout("upstream".into(), 300, 1.0).map(|_| ());
 
-    let result = TOXIPROXY.find_and_reset_proxy("socket");
-    assert!(result.is_ok());
-    assert!(result.as_ref().unwrap().proxy_pack.enabled);
+    let server_thread = echo_server();
+    let mut stream = TcpStream::connect("localhost:2001").unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let start = SystemTime::now();
+    stream.write_all(b"ping").unwrap();
+    let read = stream.read(&mut [0; 4]);
+    let elapsed = start.elapsed().unwrap();
+    drop(stream);
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
 
-    assert!(result
-        .as_ref()
+    assert!(toxic.is_ok());
+    // Closed by the proxy, before the read timeout of the client.
+    assert!(matches!(read, Ok(0) | Err(_)));
+    assert!(elapsed >= Duration::from_millis(250));
+    assert!(elapsed < Duration::from_secs(2));
+}
+
+#[test]
+fn test_proxy_with_slicer_attributes() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let toxics = proxy
+        .with_slicer(
+            "downstream".into(),
+            1024,
+            128,
+            toxic::ToxicValueType::MAX,
+            1.0,
+        )
         .unwrap()
-        .with_down(|| {
-            let result = TOXIPROXY.find_and_reset_proxy("socket");
-            assert!(result.is_ok());
-            assert!(!result.as_ref().unwrap().proxy_pack.enabled);
-        })
-        .is_ok());
+        .toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
 
-    let result = TOXIPROXY.find_and_reset_proxy("socket");
-    assert!(result.is_ok());
-    assert!(result.as_ref().unwrap().proxy_pack.enabled);
+    let toxics = toxics.unwrap();
+    assert_eq!(1, toxics.len());
+    assert_eq!("slicer", toxics[0].r#type);
+    assert_eq!(
+        Some(&serde_json::json!(1024)),
+        toxics[0].attributes.get("average_size")
+    );
+    assert_eq!(
+        Some(&serde_json::json!(128)),
+        toxics[0].attributes.get("size_variation")
+    );
+    assert_eq!(
+        Some(&serde_json::json!(toxic::ToxicValueType::MAX)),
+        toxics[0].attributes.get("delay")
+    );
 }
 
 #[test]
-fn test_proxy_apply_with_latency() {
+fn test_proxy_apply_spec() {
     populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
 
-    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
-    assert!(proxy_result.is_ok());
+    let toxic = proxy.apply_spec("downstream latency=500 jitter=50 @0.8");
+    let toxics = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
 
-    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
-    assert!(proxy_toxics.is_ok());
-    assert_eq!(0, proxy_toxics.as_ref().unwrap().len());
+    let toxic = toxic.unwrap();
+    assert_eq!("latency_downstream", toxic.name);
+    assert_eq!(Some("downstream".into()), toxic.stream);
+    assert_eq!(0.8, toxic.toxicity);
+    assert_eq!(
+        Some(&serde_json::json!(500)),
+        toxic.attributes.get("latency")
+    );
+    assert_eq!(Some(&serde_json::json!(50)), toxic.attributes.get("jitter"));
+    assert_eq!(1, toxics.unwrap().len());
 
-    let apply_result = proxy_result
-        .as_ref()
+    for spec in &[
+        "",
+        "@0.5",
+        "latency=fast",
+        "latency=1 @2",
+        "latency=1 jitter",
+        "latency=1 rate=2",
+    ] {
+        assert!(proxy.apply_spec(spec).is_err(), "{} is invalid", spec);
+    }
+    assert!(proxy.toxics().unwrap().is_empty());
+}
+
+#[test]
+fn test_all_toxics_are_bound() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy
+        .with_latency("downstream".into(), 1000, 0, 1.0)
+        .unwrap();
+
+    let updated = TOXIPROXY.all().map(|proxies| {
+        proxies["socket"].proxy_pack.toxics[0]
+            .set_latency(3000)
+            .map(|toxic| toxic.attributes["latency"].clone())
+    });
+    let toxics = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
+
+    assert_eq!(serde_json::json!(3000), updated.unwrap().unwrap());
+    assert_eq!(
+        Some(&serde_json::json!(3000)),
+        toxics.unwrap()[0].attributes.get("latency")
+    );
+}
+
+#[test]
+fn test_proxy_echo_roundtrip_with_slow_close() {
+    let server_thread = echo_server();
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+
+    let start = SystemTime::now();
+    let echo_result = proxy
+        .with_slow_close("downstream".into(), 500, 1.0)
         .unwrap()
-        .with_latency("downstream".into(), 2000, 0, 1.0)
-        .apply(|| {
-            let all = TOXIPROXY.all();
-            assert!(all.is_ok());
-            let proxy = all.as_ref().unwrap().get("socket");
-            assert!(proxy.is_some());
+        .echo_roundtrip(b"hello world");
+    let elapsed = start.elapsed().unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+    server_thread.join().expect("Failed closing server thread");
 
-            let proxy_toxics = proxy.as_ref().unwrap().toxics();
-            assert!(proxy_toxics.is_ok());
-            assert_eq!(1, proxy_toxics.as_ref().unwrap().len());
-        });
+    assert_eq!(b"hello world".to_vec(), echo_result.unwrap());
+    assert!(elapsed >= Duration::from_millis(500));
+}
 
-    assert!(apply_result.is_ok());
+#[test]
+fn test_proxy_with_reset_peer() {
+    let server_thread = echo_server();
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy.with_reset_peer("upstream".into(), 0, 1.0).unwrap();
 
-    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
-    assert!(proxy_toxics.is_ok());
-    assert_eq!(0, proxy_toxics.as_ref().unwrap().len());
+    let mut stream = TcpStream::connect("localhost:2001").unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    stream.write_all(b"ping").unwrap();
+    let read = stream.read(&mut [0; 4]);
+    let removed = proxy.remove_reset_peer("upstream");
+    server_thread.join().expect("Failed closing server thread");
+
+    assert!(removed.is_ok());
+    assert_eq!(
+        std::io::ErrorKind::ConnectionReset,
+        read.expect_err("connection is reset").kind()
+    );
 }
 
 #[test]
-fn test_proxy_apply_with_latency_as_separate_calls_for_test() {
+fn test_proxy_large_attribute() {
     populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let bytes: toxic::ToxicValueType = 10 * u32::MAX as toxic::ToxicValueType;
 
-    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
-    assert!(proxy_result.is_ok());
+    let toxic = proxy.add_toxic(
+        toxic::ToxicBuilder::new("limit_data".into())
+            .attribute("bytes".into(), bytes)
+            .build()
+            .unwrap(),
+    );
+    let toxics = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
 
-    let proxy_toxics = proxy_result.as_ref().unwrap().toxics();
-    assert!(proxy_toxics.is_ok());
-    assert_eq!(0, proxy_toxics.as_ref().unwrap().len());
+    assert_eq!(
+        Some(&serde_json::json!(bytes)),
+        toxic.unwrap().attributes.get("bytes")
+    );
+    assert_eq!(
+        Some(&serde_json::json!(bytes)),
+        toxics.unwrap()[0].attributes.get("bytes")
+    );
+}
 
-    let _ = proxy_result
-        .as_ref()
-        .unwrap()
-        .with_latency("downstream".into(), 2000, 0, 1.0);
+#[test]
+fn test_proxy_replace_toxic() {
+    populate_example();
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    let latency = |latency: toxic::ToxicValueType| {
+        toxic::ToxicBuilder::new("latency".into())
+            .attribute("latency".into(), latency)
+            .build()
+            .unwrap()
+    };
 
-    let all = TOXIPROXY.all();
-    assert!(all.is_ok());
-    let proxy = all.as_ref().unwrap().get("socket");
-    assert!(proxy.is_some());
+    let added = proxy.add_toxic(latency(1000));
+    let duplicate = proxy.add_toxic(latency(2000));
+    let kept = proxy.toxics();
+    let replaced = proxy.replace_toxic(latency(3000));
+    let toxics = proxy.toxics();
+    assert!(proxy.delete_all_toxics().is_ok());
 
-    let proxy_toxics = proxy.as_ref().unwrap().toxics();
-    assert!(proxy_toxics.is_ok());
-    assert_eq!(1, proxy_toxics.as_ref().unwrap().len());
+    assert!(added.is_ok());
+    assert!(duplicate.is_err());
+    assert_eq!(
+        Some(&serde_json::json!(1000)),
+        kept.unwrap()[0].attributes.get("latency")
+    );
+    assert_eq!(
+        Some(&serde_json::json!(3000)),
+        replaced.unwrap().attributes.get("latency")
+    );
+    let toxics = toxics.unwrap();
+    assert_eq!(1, toxics.len());
+    assert_eq!(
+        Some(&serde_json::json!(3000)),
+        toxics[0].attributes.get("latency")
+    );
 }
 
 #[test]
-fn test_proxy_apply_with_latency_with_real_request() {
-    let server_thread = spawn(|| one_take_server());
+fn test_transaction_rolls_back() {
     populate_example();
+    let socket = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
 
-    let proxy_result = TOXIPROXY.find_and_reset_proxy("socket");
-    assert!(proxy_result.is_ok());
+    let result: Result<(), error::ToxiproxyError> = TOXIPROXY.transaction(|transaction| {
+        let proxy = transaction.proxy(ProxyPack::new(
+            "transaction".into(),
+            "localhost:2010".into(),
+            "localhost:2000".into(),
+        ))?;
+        assert!(TOXIPROXY.find_proxy("transaction").is_ok());
 
-    let apply_result = proxy_result
-        .as_ref()
+        transaction.toxic(
+            &socket,
+            toxic::ToxicBuilder::new("latency".into())
+                .attribute("latency".into(), 1000)
+                .build()?,
+        )?;
+        transaction.toxic(
+            &socket,
+            toxic::ToxicBuilder::new("bandwidth".into())
+                .stream("upstream".into())
+                .attribute("rate".into(), 10)
+                .build()?,
+        )?;
+        assert_eq!(2, socket.toxics()?.len());
+        assert_eq!("transaction", proxy.proxy_pack.name);
+
+        Err("setup failed".into())
+    });
+
+    assert_eq!("setup failed", result.err().unwrap().to_string());
+    assert!(TOXIPROXY.find_proxy("transaction").is_err());
+    assert!(socket.toxics().unwrap().is_empty());
+}
+
+#[test]
+fn test_proxy_probe_upstream() {
+    let server_thread = echo_server();
+    populate_example();
+
+    let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+    proxy.with_timeout("downstream".into(), 0, 1.0).unwrap();
+
+    let reachable = proxy.probe_upstream();
+    server_thread.join().expect("Failed closing server thread");
+    let unreachable = proxy.probe_upstream();
+
+    let toxics = proxy.toxics().unwrap();
+    assert!(proxy.delete_all_toxics().is_ok());
+    assert!(reachable.unwrap());
+    assert!(!unreachable.unwrap());
+    assert_eq!(1, toxics.len());
+    assert_eq!("timeout", toxics[0].r#type);
+}
+
+#[test]
+fn test_proxy_probe_upstream_restores_lifted_toxics() {
+    let toxics = r#"[{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":1000,"jitter":0}},
+        {"name":"timeout_upstream","type":"timeout","stream":"upstream","toxicity":1,"attributes":{"timeout":0}}]"#;
+    let latency = r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1,"attributes":{"latency":1000,"jitter":0}}"#;
+    let timeout = r#"{"name":"timeout_upstream","type":"timeout","stream":"upstream","toxicity":1,"attributes":{"timeout":0}}"#;
+    let failed_lift = mock_server(vec![
+        (200, toxics),
+        (204, ""),
+        (500, r#"{"error":"internal error","status":500}"#),
+        (200, latency),
+    ]);
+    let failed_restore = mock_server(vec![
+        (200, toxics),
+        (204, ""),
+        (204, ""),
+        (409, r#"{"error":"toxic already exists","status":409}"#),
+        (200, timeout),
+    ]);
+
+    let lifted = client::Client::new(failed_lift.addr.as_str())
+        .proxy_handle("socket")
+        .probe_upstream();
+    let restored = client::Client::new(failed_restore.addr.as_str())
+        .proxy_handle("socket")
+        .probe_upstream();
+
+    assert_eq!(
+        "server error 500: internal error",
+        lifted.err().unwrap().to_string()
+    );
+    let requests = failed_lift.requests.lock().unwrap();
+    assert_eq!(4, requests.len());
+    assert!(requests[3].starts_with("POST /proxies/socket/toxics "));
+    assert!(failed_lift.bodies.lock().unwrap()[3].contains("latency_downstream"));
+
+    assert_eq!(
+        "toxic `latency_downstream` cannot be restored: server error 409: toxic already exists",
+        restored.err().unwrap().to_string()
+    );
+    let requests = failed_restore.requests.lock().unwrap();
+    assert_eq!(5, requests.len());
+    assert!(failed_restore.bodies.lock().unwrap()[4].contains("timeout_upstream"));
+}
+
+#[test]
+fn test_toxic_builder_validation() {
+    let result = toxic::ToxicBuilder::new("latency".into())
+        .attribute("latancy".into(), 2000)
+        .build();
+    assert!(result.is_err());
+    assert!(result.err().unwrap().to_string().contains("`latancy`"));
+
+    let result = toxic::ToxicBuilder::new("bandwidth".into()).build();
+    assert!(result.is_err());
+    assert!(result.err().unwrap().to_string().contains("`rate`"));
+
+    let result = toxic::ToxicBuilder::new("latency".into())
+        .stream("upstream".into())
+        .attribute("latency".into(), 2000)
+        .attribute("jitter".into(), 10)
+        .build();
+    assert!(result.is_ok());
+    assert_eq!("latency_upstream", result.unwrap().name);
+
+    let result = toxic::ToxicBuilder::new("my_plugin".into())
+        .attribute("anything".into(), 1)
+        .build();
+    assert!(result.is_ok());
+
+    let result = toxic::ToxicBuilder::new("latency".into())
+        .attribute("latency".into(), "2000")
+        .build();
+    assert!(result.is_err());
+    assert!(result
+        .err()
         .unwrap()
-        .with_latency("downstream".into(), 2000, 0, 1.0)
-        .apply(|| {
-            let client_thread = spawn(|| one_shot_client());
+        .to_string()
+        .contains("integer for `latency`"));
 
-            server_thread.join().expect("Failed closing server thread");
-            let duration = client_thread.join().expect("Failed closing client thread");
+    let result = toxic::ToxicBuilder::new("latency".into())
+        .attribute("latency".into(), -2000)
+        .attribute("jitter".into(), i64::MAX)
+        .build();
+    assert!(result.is_ok());
+    let toxic = result.unwrap();
+    assert_eq!(Some(-2000), toxic.integer_attribute("latency"));
+    assert_eq!(Some(i64::MAX), toxic.integer_attribute("jitter"));
+}
 
-            assert!(duration.as_secs() >= 2);
-        });
+#[test]
+fn test_toxic_without_stream() {
+    let toxic = toxic::ToxicBuilder::new("my_plugin".into())
+        .without_stream()
+        .attribute("mode".into(), "drop")
+        .build()
+        .unwrap();
+    assert_eq!("my_plugin", toxic.name);
 
-    assert!(apply_result.is_ok());
+    let json = serde_json::to_value(&toxic).unwrap();
+    assert!(json.get("stream").is_none());
+    assert_eq!(serde_json::json!("my_plugin"), json["type"]);
+
+    let toxic: toxic::ToxicPack = serde_json::from_value(json).unwrap();
+    assert_eq!(None, toxic.stream);
 }
 
-/**
+#[test]
+fn test_toxic_serde_round_trip() {
+    let server = mock_server(vec![(
+        200,
+        r#"{"name":"socket","listen":"127.0.0.1:2001","upstream":"localhost:2000","enabled":true,
+            "toxics":[{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":0.5,"attributes":{"latency":1000,"jitter":0}}]}"#,
+    )]);
+    let proxy = client::Client::new(server.addr.as_str())
+        .find_proxy("socket")
+        .unwrap();
+    // Bound to the proxy, which must not leak into the JSON.
+    let toxic = &proxy.proxy_pack.toxics[0];
+
+    let json = serde_json::to_value(toxic).unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "name": "latency_downstream",
+            "type": "latency",
+            "stream": "downstream",
+            "toxicity": 0.5,
+            "attributes": {"latency": 1000, "jitter": 0},
+        }),
+        json
+    );
+
+    let decoded: toxic::ToxicPack = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!("latency_downstream", decoded.name);
+    assert_eq!(toxic.attributes, decoded.attributes);
+    assert_eq!(json, serde_json::to_value(&decoded).unwrap());
+}
+
+#[test]
+fn test_toxic_mixed_attributes() {
+    let toxic = toxic::ToxicBuilder::new("jitter_plugin".into())
+        .attribute("count".into(), 3)
+        .attribute("ratio".into(), 0.25)
+        .attribute("mode".into(), "burst")
+        .build()
+        .unwrap();
+
+    let json = serde_json::to_string(&toxic).unwrap();
+    let decoded: toxic::ToxicPack = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(Some(3), decoded.integer_attribute("count"));
+    assert_eq!(Some(3.0), decoded.float_attribute("count"));
+    assert_eq!(Some(0.25), decoded.float_attribute("ratio"));
+    assert_eq!(None, decoded.integer_attribute("ratio"));
+    assert_eq!(Some("burst"), decoded.string_attribute("mode"));
+    assert_eq!(toxic.attributes, decoded.attributes);
+}
+
+#[test]
+fn test_toxic_lenient_attribute_numbers() {
+    let toxic: toxic::ToxicPack = serde_json::from_str(
+        r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":2000.0,"jitter":0}}"#,
+    )
+    .unwrap();
+    assert_eq!(Some(2000), toxic.attributes["latency"].as_u64());
+
+    let result = serde_json::from_str::<toxic::ToxicPack>(
+        r#"{"name":"latency_downstream","type":"latency","stream":"downstream","toxicity":1.0,"attributes":{"latency":2000.5}}"#,
+    );
+    assert!(result.is_err());
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("integer for `latency`"));
+
+    let toxic: toxic::ToxicPack = serde_json::from_str(
+        r#"{"name":"my_plugin_downstream","type":"my_plugin","stream":"downstream","toxicity":1.0,"attributes":{"ratio":0.5}}"#,
+    )
+    .unwrap();
+    assert_eq!(Some(0.5), toxic.attributes["ratio"].as_f64());
+}
+
+#[test]
+fn test_toxic_unknown_type_keeps_floats() {
+    let json = serde_json::json!({
+        "name": "my_plugin_downstream",
+        "type": "my_plugin",
+        "stream": "downstream",
+        "toxicity": 1.0,
+        "attributes": {"factor": 2.0, "count": 3},
+    });
+
+    let toxic: toxic::ToxicPack = serde_json::from_value(json.clone()).unwrap();
+    assert!(toxic.attributes["factor"].is_f64());
+    assert_eq!(Some(2.0), toxic.attributes["factor"].as_f64());
+    assert!(toxic.attributes["count"].is_u64());
+    assert_eq!(json, serde_json::to_value(&toxic).unwrap());
+}
+
+#[test]
+fn test_toxic_builder_custom_attributes() {
+    let toxic = toxic::ToxicBuilder::new("my_plugin".into())
+        .attribute("mode".into(), "drop")
+        .attribute("enabled".into(), true)
+        .attribute("ratio".into(), 0.25)
+        .attribute("limits".into(), serde_json::json!({"max": 10}))
+        .build()
+        .unwrap();
+
+    let json = serde_json::to_value(&toxic).unwrap();
+    assert_eq!(
+        serde_json::json!({"mode": "drop", "enabled": true, "ratio": 0.25, "limits": {"max": 10}}),
+        json["attributes"]
+    );
+
+    let toxic: toxic::ToxicPack = serde_json::from_value(json).unwrap();
+    assert_eq!(Some("drop"), toxic.attributes["mode"].as_str());
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_toxic_lifecycle_is_traced() {
+    populate_example();
+
+    let subscriber = AuditSubscriber::default();
+    let events = subscriber.events.clone();
+
+    tracing::subscriber::with_default(subscriber, || {
+        let proxy = TOXIPROXY.find_and_reset_proxy("socket").unwrap();
+        proxy
+            .with_latency("downstream".into(), 2000, 0, 1.0)
+            .unwrap();
+        assert!(proxy.delete_all_toxics().is_ok());
+    });
+
+    let events = events.lock().unwrap();
+    let actions = events
+        .iter()
+        .map(|event| event["action"].as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(vec!["created", "removed"], actions);
+
+    for event in events.iter() {
+        assert_eq!("socket", event["proxy"]);
+        assert_eq!("latency", event["toxic_type"]);
+        assert_eq!("downstream", event["stream"]);
+        assert!(event["attributes"].contains("\"latency\":2000"));
+    }
+}
+
+/*
  * Support functions.
  */
 
@@ -193,10 +3021,10 @@ fn one_shot_client() -> Duration {
     let mut stream = TcpStream::connect("localhost:2001").expect("Failed to connect to server");
 
     stream
-        .write("hello".as_bytes())
+        .write_all("hello".as_bytes())
         .expect("Client failed sending request");
 
-    stream
+    let _ = stream
         .read(&mut [0u8; 1024])
         .expect("Client failed reading response");
 
@@ -211,13 +3039,198 @@ fn one_take_server() {
         .expect("Failed to listen for incoming")
         .expect("Request failes");
 
-    stream
+    let _ = stream
         .read(&mut [0u8; 1024])
         .expect("Server failed reading request");
 
     stream
-        .write("byebye".as_bytes())
+        .write_all("byebye".as_bytes())
         .expect("Server failed writing response");
 
     stream.flush().expect("Failed flushing connection");
 }
+
+fn echo_server() -> std::thread::JoinHandle<()> {
+    let listener = TcpListener::bind("localhost:2000").expect("TcpListener cannot connect");
+
+    spawn(move || {
+        let mut stream = listener
+            .incoming()
+            .next()
+            .expect("Failed to listen for incoming")
+            .expect("Request failes");
+
+        let mut buffer = [0u8; 1024];
+        while let Ok(read) = stream.read(&mut buffer) {
+            if read == 0 || stream.write_all(&buffer[..read]).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct AuditSubscriber {
+    events: std::sync::Arc<std::sync::Mutex<Vec<std::collections::HashMap<String, String>>>>,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for AuditSubscriber {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        metadata.target() == "toxiproxy_rust::audit"
+    }
+
+    fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        struct Fields<'a>(&'a mut std::collections::HashMap<String, String>);
+
+        impl<'a> tracing::field::Visit for Fields<'a> {
+            fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                self.0.insert(field.name().into(), value.into());
+            }
+
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0.insert(field.name().into(), format!("{:?}", value));
+            }
+        }
+
+        let mut fields = std::collections::HashMap::new();
+        event.record(&mut Fields(&mut fields));
+        self.events.lock().unwrap().push(fields);
+    }
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
+}
+
+struct MockServer {
+    addr: String,
+    requests: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    headers: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    bodies: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+/// Serves the given `(status, body)` responses to consecutive connections and records
+/// the request lines, headers and bodies it received.
+fn mock_server(responses: Vec<(u16, &'static str)>) -> MockServer {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("TcpListener cannot connect");
+    let addr = listener.local_addr().unwrap().to_string();
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded = requests.clone();
+    let headers = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded_headers = headers.clone();
+    let bodies = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded_bodies = bodies.clone();
+
+    spawn(move || {
+        for (status, body) in responses {
+            let mut stream = match listener.incoming().next() {
+                Some(Ok(stream)) => stream,
+                _ => return,
+            };
+
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request).to_string();
+            let content_length = request
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|len| len.trim().parse::<usize>().unwrap_or(0))
+                })
+                .unwrap_or(0);
+            let mut received_body = request
+                .split("\r\n\r\n")
+                .nth(1)
+                .unwrap_or("")
+                .as_bytes()
+                .to_vec();
+            while received_body.len() < content_length {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => received_body.extend_from_slice(&buf[..n]),
+                }
+            }
+            recorded_bodies
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(&received_body).to_string());
+            recorded_headers.lock().unwrap().push(
+                request
+                    .split("\r\n\r\n")
+                    .next()
+                    .unwrap_or("")
+                    .lines()
+                    .skip(1)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+            recorded
+                .lock()
+                .unwrap()
+                .push(request.lines().next().unwrap_or("").to_owned());
+
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    MockServer {
+        addr,
+        requests,
+        headers,
+        bodies,
+    }
+}
+
+struct CaptureLogger {
+    logs: std::sync::Mutex<Vec<String>>,
+}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.logs.lock().unwrap().push(format!("{}", record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs a global logger (once) and returns the messages it captured so far.
+fn captured_logs() -> &'static std::sync::Mutex<Vec<String>> {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    static LOGGER: CaptureLogger = CaptureLogger {
+        logs: std::sync::Mutex::new(vec![]),
+    };
+
+    INIT.call_once(|| {
+        log::set_logger(&LOGGER).expect("logger cannot be set");
+        log::set_max_level(log::LevelFilter::Warn);
+    });
+
+    &LOGGER.logs
+}